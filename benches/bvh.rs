@@ -0,0 +1,137 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use rand::{rngs::SmallRng, Rng, SeedableRng};
+use raytracer::background::Background;
+use raytracer::bvh::BvhNode;
+use raytracer::camera::{calculate_viewport, Camera};
+use raytracer::data::{Hittable, Ray};
+use raytracer::interval::Interval;
+use raytracer::linear_bvh::LinearBvh;
+use raytracer::materials::Material;
+use raytracer::scene_file::load_scene;
+use raytracer::shapes::sphere::Sphere;
+use raytracer::texture::Texture;
+use raytracer::Scene;
+use std::hint::black_box;
+use std::path::Path;
+use vek::{Rgb, Vec2, Vec3};
+
+const SCENES: [&str; 4] = ["three_spheres", "cornell_box", "triangles", "obj"];
+const RAY_COUNT: usize = 4096;
+
+/// Rays from the camera through random points on a 256x256 image
+fn camera_rays(scene: &Scene, rng: &mut impl Rng) -> Vec<Ray> {
+    let image_size = Vec2::new(256, 256);
+    let viewport = calculate_viewport(scene.camera.clone(), image_size);
+
+    (0..RAY_COUNT)
+        .map(|_| {
+            let position = Vec2::new(rng.gen_range(0. ..256.), rng.gen_range(0. ..256.));
+
+            let pixel = viewport.upper_left_pixel_position
+                + position.x * viewport.horizontal_pixel_delta
+                + position.y * viewport.vertical_pixel_delta;
+
+            Ray::new(viewport.origin, pixel - viewport.origin, 0.)
+        })
+        .collect()
+}
+
+/// The spheres of the many_spheres binary, with diffuse materials only
+fn many_spheres() -> Scene {
+    let camera = Camera {
+        position: Vec3::new(13., 2., 3.),
+        target: Vec3::new(0., 0., 0.),
+        up: Vec3::new(0., 1., 0.),
+
+        vertical_fov: (20_f32).to_radians(),
+        defocus_angle: 0.,
+        focus_distance: 10.,
+
+        shutter_open: 0.,
+        shutter_close: 1.,
+    };
+
+    let material = Material::Diffuse {
+        albedo: Texture::solid(Rgb::new(0.5, 0.5, 0.5)),
+    };
+
+    let mut spheres = vec![
+        Sphere::new(Vec3::new(0., -1000., 0.), 1000., material.clone()),
+        Sphere::new(Vec3::new(0., 1., 0.), 1., material.clone()),
+        Sphere::new(Vec3::new(-4., 1., 0.), 1., material.clone()),
+        Sphere::new(Vec3::new(4., 1., 0.), 1., material.clone()),
+    ];
+
+    let rng = &mut SmallRng::seed_from_u64(0);
+
+    for a in -11..11 {
+        for b in -11..11 {
+            let moving = rng.gen::<f32>() < 0.8;
+
+            let center = Vec3::new(
+                a as f32 + 0.9 * rng.gen::<f32>(),
+                0.2,
+                b as f32 + 0.9 * rng.gen::<f32>(),
+            );
+            if center.distance(Vec3::new(4., 0.2, 0.)) > 0.9 {
+                if moving {
+                    let end = center + Vec3::new(0., rng.gen_range(0. ..0.5), 0.);
+                    spheres.push(Sphere::moving(center, end, 0.2, material.clone()));
+                } else {
+                    spheres.push(Sphere::new(center, 0.2, material.clone()));
+                }
+            }
+        }
+    }
+
+    let mut scene = Scene {
+        camera,
+        background: Background::Color(Rgb::new(0.7, 0.8, 1.)),
+        ..Default::default()
+    };
+    scene.extend(spheres);
+
+    scene
+}
+
+fn cast_all(bvh: &impl Hittable, rays: &[Ray]) -> usize {
+    let interval = Interval::new(0.001, f32::INFINITY);
+    let rng = &mut SmallRng::seed_from_u64(0);
+
+    rays.iter()
+        .filter(|&&ray| bvh.raycast(ray, interval, rng).is_some())
+        .count()
+}
+
+fn raycast(c: &mut Criterion) {
+    let mut group = c.benchmark_group("raycast");
+
+    let scenes = SCENES.map(|name| {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("scenes")
+            .join(name)
+            .with_extension("toml");
+
+        (name, load_scene(path).unwrap())
+    });
+
+    for (name, scene) in scenes.into_iter().chain([("many_spheres", many_spheres())]) {
+        let rays = camera_rays(&scene, &mut SmallRng::seed_from_u64(0));
+
+        let tree = BvhNode::new_sah(&scene.objects).unwrap();
+        let linear = LinearBvh::from(BvhNode::new_sah(&scene.objects).unwrap());
+
+        group.bench_with_input(BenchmarkId::new("tree", name), &rays, |b, rays| {
+            b.iter(|| cast_all(&tree, black_box(rays)))
+        });
+
+        group.bench_with_input(BenchmarkId::new("linear", name), &rays, |b, rays| {
+            b.iter(|| cast_all(&linear, black_box(rays)))
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, raycast);
+criterion_main!(benches);
@@ -0,0 +1,410 @@
+use crate::settings::RenderSettings;
+use crate::tiles::Tile;
+use image::{Rgb32FImage, RgbImage};
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Read, Seek, Write};
+use std::path::Path;
+use vek::{Rgb, Vec2};
+
+/// Identifies accumulation files, and their version
+const MAGIC: &[u8; 8] = b"RTACCUM4";
+
+/// Samples, then the sum of each channel, the mean and m2
+const PIXEL_BYTES: u64 = 4 + 5 * 8;
+
+/// Pixels darker than this are judged on their absolute error, noise there is hard to see
+const DARK_LUMINANCE: f64 = 0.05;
+
+/// Samples taken so far for one pixel, with a running mean and variance of their luminance
+/// using Welford's algorithm
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PixelStats {
+    /// In f64 since a sum of thousands of samples loses the last ones in f32
+    pub sum: Rgb<f64>,
+    pub samples: u32,
+
+    pub mean: f64,
+
+    /// Sum of squared differences to the mean
+    pub m2: f64,
+}
+
+impl PixelStats {
+    pub fn add_sample(&mut self, color: Rgb<f32>) {
+        let luminance = luminance(color) as f64;
+
+        self.sum += color.as_::<f64>();
+        self.samples += 1;
+
+        let delta = luminance - self.mean;
+        self.mean += delta / self.samples as f64;
+        self.m2 += delta * (luminance - self.mean);
+    }
+
+    /// Combines the statistics of two sets of samples, Chan et al.'s parallel variant
+    pub fn merge(&mut self, other: PixelStats) {
+        if other.samples == 0 {
+            return;
+        }
+
+        let samples = self.samples + other.samples;
+        let delta = other.mean - self.mean;
+        let weight = self.samples as f64 * other.samples as f64 / samples as f64;
+
+        self.sum += other.sum;
+        self.mean += delta * other.samples as f64 / samples as f64;
+        self.m2 += other.m2 + delta * delta * weight;
+        self.samples = samples;
+    }
+
+    pub fn color(&self) -> Rgb<f32> {
+        (self.sum / self.samples.max(1) as f64).as_::<f32>()
+    }
+
+    /// Standard error of the mean luminance, infinite until there are two samples
+    pub fn standard_error(&self) -> f64 {
+        if self.samples < 2 {
+            return f64::INFINITY;
+        }
+
+        let variance = self.m2 / (self.samples - 1) as f64;
+
+        f64::sqrt(variance / self.samples as f64)
+    }
+
+    /// Standard error relative to the mean luminance
+    pub fn relative_error(&self) -> f64 {
+        self.standard_error() / self.mean.max(DARK_LUMINANCE)
+    }
+}
+
+/// FNV-1a hash of a scene file's contents. Files it refers to, like meshes and images,
+/// aren't part of it
+pub fn scene_hash(contents: &[u8]) -> u64 {
+    contents.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &byte| {
+        (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+fn luminance(color: Rgb<f32>) -> f32 {
+    0.2126 * color.r + 0.7152 * color.g + 0.0722 * color.b
+}
+
+/// Every sample taken so far for each pixel, so a render can go on in more passes
+#[derive(Debug, Clone)]
+pub struct Accumulation {
+    pub size: Vec2<u32>,
+
+    /// Row by row
+    pub pixels: Vec<PixelStats>,
+    pub passes: u32,
+
+    /// Base seed of the render, kept so a resumed render continues the same sequence
+    pub seed: u64,
+
+    /// `settings.samples_per_pixel` of the render
+    pub samples_per_pixel: u32,
+
+    /// `scene_hash` of the scene file, to tell whether a resumed render is of the same scene
+    pub scene_hash: u64,
+}
+
+impl Accumulation {
+    /// Draws the base seed from `settings`
+    pub fn new(settings: &RenderSettings, scene_hash: u64) -> Self {
+        let size = settings.image_size();
+
+        Self {
+            size,
+            pixels: vec![PixelStats::default(); (size.x * size.y) as usize],
+            passes: 0,
+            seed: settings.base_seed(),
+            samples_per_pixel: settings.samples_per_pixel,
+            scene_hash,
+        }
+    }
+
+    /// Why a render with `settings` of the scene with `scene_hash` can't continue from this,
+    /// if it can't
+    pub fn resume_mismatch(&self, settings: &RenderSettings, scene_hash: u64) -> Option<String> {
+        let size = settings.image_size();
+
+        if self.size != size {
+            Some(format!(
+                "it is {}x{}, not {}x{}",
+                self.size.x, self.size.y, size.x, size.y
+            ))
+        } else if settings.seed.is_some_and(|seed| seed != self.seed) {
+            Some(format!("it was rendered with seed {}", self.seed))
+        } else if self.samples_per_pixel != settings.samples_per_pixel {
+            Some(format!(
+                "it was rendered with {} samples per pixel",
+                self.samples_per_pixel
+            ))
+        } else if self.scene_hash != scene_hash {
+            Some("it is of a different scene".to_string())
+        } else {
+            None
+        }
+    }
+
+    /// Adds the samples of each tile's pixels, which are row by row
+    pub fn add_pass(&mut self, tiles: Vec<(Tile, Vec<PixelStats>)>) {
+        for (tile, pixels) in tiles {
+            for (position, pass) in tile.pixels().zip(pixels) {
+                self.pixels[(position.y * self.size.x + position.x) as usize].merge(pass);
+            }
+        }
+
+        self.passes += 1;
+    }
+
+    /// Fewest samples any pixel has
+    pub fn min_samples(&self) -> u32 {
+        self.pixels
+            .iter()
+            .map(|pixel| pixel.samples)
+            .min()
+            .unwrap_or(0)
+    }
+
+    /// The average of the samples
+    pub fn image(&self) -> Rgb32FImage {
+        Rgb32FImage::from_fn(self.size.x, self.size.y, |x, y| {
+            let pixel = self.pixels[(y * self.size.x + x) as usize];
+
+            image::Rgb(pixel.color().into_array())
+        })
+    }
+
+    /// Heat map of the samples each pixel got, from black for the fewest through red and
+    /// yellow to white for the most
+    pub fn sample_map(&self) -> RgbImage {
+        let max_samples = self
+            .pixels
+            .iter()
+            .map(|pixel| pixel.samples)
+            .max()
+            .unwrap_or(0)
+            .max(1);
+
+        RgbImage::from_fn(self.size.x, self.size.y, |x, y| {
+            let samples = self.pixels[(y * self.size.x + x) as usize].samples;
+            let t = samples as f32 / max_samples as f32;
+
+            let heat = Rgb::new(3. * t, 3. * t - 1., 3. * t - 2.);
+
+            image::Rgb(
+                heat.map(|c| (c.clamp(0., 1.) * 255.).round() as u8)
+                    .into_array(),
+            )
+        })
+    }
+
+    /// Writes next to `path` first, so a crash while saving leaves the previous checkpoint
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let path = path.as_ref();
+        let temporary = path.with_extension("tmp");
+
+        self.write(&temporary)?;
+
+        fs::rename(temporary, path)
+    }
+
+    fn write(&self, path: &Path) -> io::Result<()> {
+        let mut file = BufWriter::new(File::create(path)?);
+
+        file.write_all(MAGIC)?;
+
+        for value in [self.size.x, self.size.y, self.passes] {
+            file.write_all(&value.to_le_bytes())?;
+        }
+
+        file.write_all(&self.samples_per_pixel.to_le_bytes())?;
+
+        for value in [self.seed, self.scene_hash] {
+            file.write_all(&value.to_le_bytes())?;
+        }
+
+        for pixel in &self.pixels {
+            file.write_all(&pixel.samples.to_le_bytes())?;
+
+            for value in [pixel.sum.r, pixel.sum.g, pixel.sum.b, pixel.mean, pixel.m2] {
+                file.write_all(&value.to_le_bytes())?;
+            }
+        }
+
+        file.flush()?;
+        file.get_ref().sync_all()
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        let mut file = BufReader::new(File::open(path)?);
+
+        let mut magic = [0; 8];
+        file.read_exact(&mut magic)?;
+
+        if &magic != MAGIC {
+            return Err(invalid_data("not an accumulation file"));
+        }
+
+        let read_u32 = |file: &mut BufReader<File>| -> io::Result<u32> {
+            let mut bytes = [0; 4];
+            file.read_exact(&mut bytes)?;
+            Ok(u32::from_le_bytes(bytes))
+        };
+
+        let read_u64 = |file: &mut BufReader<File>| -> io::Result<u64> {
+            let mut bytes = [0; 8];
+            file.read_exact(&mut bytes)?;
+            Ok(u64::from_le_bytes(bytes))
+        };
+
+        let read_f64 = |file: &mut BufReader<File>| -> io::Result<f64> {
+            let mut bytes = [0; 8];
+            file.read_exact(&mut bytes)?;
+            Ok(f64::from_le_bytes(bytes))
+        };
+
+        let size = Vec2::new(read_u32(&mut file)?, read_u32(&mut file)?);
+        let passes = read_u32(&mut file)?;
+
+        let samples_per_pixel = read_u32(&mut file)?;
+        let seed = read_u64(&mut file)?;
+        let scene_hash = read_u64(&mut file)?;
+
+        // Checked before allocating, a corrupt header could ask for any amount of memory
+        let remaining = file.get_ref().metadata()?.len() - file.stream_position()?;
+        let pixel_count = (size.x as u64)
+            .checked_mul(size.y as u64)
+            .filter(|count| count.checked_mul(PIXEL_BYTES) == Some(remaining))
+            .ok_or_else(|| invalid_data("accumulation file doesn't match its image size"))?;
+
+        let mut pixels = Vec::with_capacity(pixel_count as usize);
+
+        for _ in 0..pixel_count {
+            let samples = read_u32(&mut file)?;
+            let sum = Rgb::new(
+                read_f64(&mut file)?,
+                read_f64(&mut file)?,
+                read_f64(&mut file)?,
+            );
+
+            pixels.push(PixelStats {
+                sum,
+                samples,
+                mean: read_f64(&mut file)?,
+                m2: read_f64(&mut file)?,
+            });
+        }
+
+        Ok(Self {
+            size,
+            pixels,
+            passes,
+            seed,
+            samples_per_pixel,
+            scene_hash,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn path(test: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("raytracer_accumulation_{test}.bin"))
+    }
+
+    /// A 3x2 accumulation with a different amount of samples in each pixel
+    fn accumulation() -> Accumulation {
+        let settings = RenderSettings {
+            width: 3,
+            aspect_ratio: 1.5,
+            seed: Some(7),
+            ..Default::default()
+        };
+
+        let mut accumulation = Accumulation::new(&settings, scene_hash(b"scene"));
+
+        for (index, pixel) in accumulation.pixels.iter_mut().enumerate() {
+            for sample in 0..index {
+                pixel.add_sample(Rgb::new(0.1, 0.5, 2.) * sample as f32);
+            }
+        }
+        accumulation.passes = 3;
+
+        accumulation
+    }
+
+    fn assert_same(a: &Accumulation, b: &Accumulation) {
+        assert_eq!(a.size, b.size);
+        assert_eq!(a.passes, b.passes);
+        assert_eq!(a.seed, b.seed);
+        assert_eq!(a.samples_per_pixel, b.samples_per_pixel);
+        assert_eq!(a.scene_hash, b.scene_hash);
+        assert_eq!(a.pixels.len(), b.pixels.len());
+
+        for (a, b) in a.pixels.iter().zip(&b.pixels) {
+            assert_eq!(a.samples, b.samples);
+            assert_eq!(a.sum, b.sum);
+            assert_eq!(a.mean.to_bits(), b.mean.to_bits());
+            assert_eq!(a.m2.to_bits(), b.m2.to_bits());
+        }
+    }
+
+    #[test]
+    fn save_and_load_round_trip() {
+        let path = path("round_trip");
+        let accumulation = accumulation();
+        assert_eq!(accumulation.size, Vec2::new(3, 2));
+
+        accumulation.save(&path).unwrap();
+        assert!(!path.with_extension("tmp").exists());
+
+        assert_same(&Accumulation::load(&path).unwrap(), &accumulation);
+    }
+
+    #[test]
+    fn load_rejects_truncated_and_padded_files() {
+        let path = path("truncated");
+        accumulation().save(&path).unwrap();
+        let bytes = fs::read(&path).unwrap();
+
+        // In the magic, the header, the first pixel and one byte short of the end
+        for length in [4, 20, 40, 50, bytes.len() - 1] {
+            fs::write(&path, &bytes[..length]).unwrap();
+            assert!(Accumulation::load(&path).is_err(), "{length} bytes");
+        }
+
+        fs::write(&path, [&bytes[..], &[0]].concat()).unwrap();
+        let error = Accumulation::load(&path).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn load_rejects_sizes_the_file_does_not_hold() {
+        let path = path("huge");
+        accumulation().save(&path).unwrap();
+        let mut bytes = fs::read(&path).unwrap();
+
+        // Width and height of u32::MAX, without the pixels to go with them
+        bytes[8..16].fill(0xff);
+        fs::write(&path, &bytes).unwrap();
+
+        let error = Accumulation::load(&path).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+
+        bytes[..8].copy_from_slice(b"RTACCUM0");
+        fs::write(&path, &bytes).unwrap();
+
+        let error = Accumulation::load(&path).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+}
@@ -0,0 +1,221 @@
+use crate::sky::Sky;
+use image::Rgb32FImage;
+use rand::Rng;
+use std::f32::consts::PI;
+use std::sync::Arc;
+use vek::{Rgb, Vec3};
+
+/// What rays that don't hit anything see
+#[derive(Debug, Clone)]
+pub enum Background {
+    Color(Rgb<f32>),
+    Environment(Arc<EnvironmentMap>),
+    Sky(Arc<Sky>),
+}
+
+impl Default for Background {
+    fn default() -> Self {
+        Background::Color(Rgb::zero())
+    }
+}
+
+impl Background {
+    /// Light arriving from `direction`, which must be unit length
+    pub fn radiance(&self, direction: Vec3<f32>) -> Rgb<f32> {
+        match self {
+            &Background::Color(color) => color,
+            Background::Environment(environment) => environment.radiance(direction),
+            Background::Sky(sky) => sky.radiance(direction),
+        }
+    }
+
+    /// Whether the integrator should sample it like a light
+    pub fn is_sampled(&self) -> bool {
+        match self {
+            Background::Color(_) => false,
+            Background::Environment(environment) => environment.rows.total > 0.,
+            Background::Sky(_) => true,
+        }
+    }
+
+    /// Unit length direction, picked with a density of `pdf`
+    pub fn sample_direction(&self, rng: &mut impl Rng) -> Vec3<f32> {
+        match self {
+            Background::Color(_) => Vec3::unit_y(),
+            Background::Environment(environment) => environment.sample_direction(rng),
+            Background::Sky(sky) => sky.sample_direction(rng),
+        }
+    }
+
+    /// Density of `sample_direction` picking `direction`, per solid angle
+    pub fn pdf(&self, direction: Vec3<f32>) -> f32 {
+        match self {
+            Background::Color(_) => 0.,
+            Background::Environment(environment) => environment.pdf(direction),
+            Background::Sky(sky) => sky.pdf(direction),
+        }
+    }
+}
+
+/// Equirectangular image surrounding the scene, with y up and the middle of the image
+/// towards -z. Directions are picked with odds proportional to the luminance they see
+#[derive(Debug)]
+pub struct EnvironmentMap {
+    image: Arc<Rgb32FImage>,
+
+    /// Around the y axis, in radians
+    rotation: f32,
+    strength: f32,
+
+    /// Odds of each row, then of each pixel of the row
+    rows: Distribution,
+    columns: Vec<Distribution>,
+}
+
+impl EnvironmentMap {
+    pub fn new(image: Arc<Rgb32FImage>, rotation: f32, strength: f32) -> Self {
+        let (width, height) = image.dimensions();
+
+        let columns = (0..height)
+            .map(|y| {
+                // Rows near the poles cover less of the sphere
+                let theta = (y as f32 + 0.5) / height as f32 * PI;
+                let sin_theta = theta.sin();
+
+                Distribution::new((0..width).map(|x| {
+                    let pixel = Rgb::from(image.get_pixel(x, y).0);
+                    luminance(pixel) * sin_theta
+                }))
+            })
+            .collect::<Vec<_>>();
+
+        let rows = Distribution::new(columns.iter().map(|row| row.total));
+
+        Self {
+            image,
+            rotation,
+            strength,
+            rows,
+            columns,
+        }
+    }
+
+    /// Pixel seen in `direction`, and the sine of its angle to the y axis
+    fn pixel(&self, direction: Vec3<f32>) -> (u32, u32, f32) {
+        let direction = rotate_y(direction, -self.rotation);
+        let (width, height) = self.image.dimensions();
+
+        let u = 0.5 + f32::atan2(direction.x, -direction.z) / (2. * PI);
+        let theta = direction.y.clamp(-1., 1.).acos();
+        let v = theta / PI;
+
+        let x = ((u * width as f32) as u32).min(width - 1);
+        let y = ((v * height as f32) as u32).min(height - 1);
+
+        (x, y, theta.sin())
+    }
+
+    pub fn radiance(&self, direction: Vec3<f32>) -> Rgb<f32> {
+        let (x, y, _) = self.pixel(direction);
+
+        Rgb::from(self.image.get_pixel(x, y).0) * self.strength
+    }
+
+    pub fn sample_direction(&self, rng: &mut impl Rng) -> Vec3<f32> {
+        let (width, height) = self.image.dimensions();
+
+        let y = self.rows.sample(rng.gen());
+        let x = self.columns[y].sample(rng.gen());
+
+        // Anywhere inside the pixel
+        let u = (x as f32 + rng.gen::<f32>()) / width as f32;
+        let v = (y as f32 + rng.gen::<f32>()) / height as f32;
+
+        let phi = (u - 0.5) * 2. * PI;
+        let theta = v * PI;
+
+        let direction = Vec3::new(
+            theta.sin() * phi.sin(),
+            theta.cos(),
+            -theta.sin() * phi.cos(),
+        );
+
+        rotate_y(direction, self.rotation)
+    }
+
+    pub fn pdf(&self, direction: Vec3<f32>) -> f32 {
+        let (x, y, sin_theta) = self.pixel(direction);
+
+        if sin_theta <= 0. || self.rows.total <= 0. {
+            return 0.;
+        }
+
+        let (width, height) = self.image.dimensions();
+
+        let probability =
+            self.rows.probability(y as usize) * self.columns[y as usize].probability(x as usize);
+
+        // From the density over the image to the one over the sphere
+        probability * (width * height) as f32 / (2. * PI * PI * sin_theta)
+    }
+}
+
+fn luminance(color: Rgb<f32>) -> f32 {
+    0.2126 * color.r + 0.7152 * color.g + 0.0722 * color.b
+}
+
+fn rotate_y(direction: Vec3<f32>, angle: f32) -> Vec3<f32> {
+    let (sin, cos) = angle.sin_cos();
+
+    Vec3::new(
+        direction.x * cos + direction.z * sin,
+        direction.y,
+        direction.z * cos - direction.x * sin,
+    )
+}
+
+/// Picks indices with odds proportional to their weight
+#[derive(Debug)]
+struct Distribution {
+    weights: Vec<f32>,
+    cumulative: Vec<f32>,
+    total: f32,
+}
+
+impl Distribution {
+    fn new(weights: impl IntoIterator<Item = f32>) -> Self {
+        let weights = weights.into_iter().collect::<Vec<_>>();
+
+        let mut total = 0.;
+        let cumulative = weights
+            .iter()
+            .map(|weight| {
+                total += weight;
+                total
+            })
+            .collect();
+
+        Self {
+            weights,
+            cumulative,
+            total,
+        }
+    }
+
+    /// `random` is from 0 to 1
+    fn sample(&self, random: f32) -> usize {
+        let target = random * self.total;
+
+        self.cumulative
+            .partition_point(|&sum| sum <= target)
+            .min(self.weights.len() - 1)
+    }
+
+    fn probability(&self, index: usize) -> f32 {
+        if self.total <= 0. {
+            return 0.;
+        }
+
+        self.weights[index] / self.total
+    }
+}
@@ -1,22 +1,31 @@
-use rand::{thread_rng, Rng};
+use clap::Parser;
+use rand::Rng;
+use raytracer::background::Background;
 use raytracer::camera::Camera;
 use raytracer::extensions::RngExtension;
+use raytracer::image_io::save_image;
 use raytracer::materials::Material;
+use raytracer::settings::{stream_rng, RenderSettings};
 use raytracer::shapes::sphere::Sphere;
 use raytracer::texture::Texture;
+use raytracer::tone_mapping::DisplaySettings;
 use raytracer::{render_image, Scene};
 use vek::{Rgb, Vec3};
 
 fn main() {
+    let settings = RenderSettings::parse();
+
     let camera = Camera {
         position: Vec3::new(13., 2., 3.),
         target: Vec3::new(0., 0., 0.),
         up: Vec3::new(0., 1., 0.),
 
-        background_color: Rgb::new(0.7, 0.8, 1.),
         vertical_fov: (20_f32).to_radians(),
         defocus_angle: (0_f32).to_radians(),
         focus_distance: 10.,
+
+        shutter_open: 0.,
+        shutter_close: 1.,
     };
 
     let mut spheres = vec![
@@ -35,6 +44,10 @@ fn main() {
             1.,
             Material::Glass {
                 refraction_index: 1.5,
+                roughness: 0.,
+                tint: Rgb::white(),
+                absorption: Rgb::zero(),
+                dispersion: None,
             },
         ),
         // Left sphere
@@ -51,12 +64,13 @@ fn main() {
             1.,
             Material::Metal {
                 albedo: Texture::solid(Rgb::new(0.7, 0.6, 0.5)),
-                fuzz: 0.,
+                roughness: 0.,
+                anisotropy: 0.,
             },
         ),
     ];
 
-    let rng = &mut thread_rng();
+    let rng = &mut stream_rng(settings.base_seed(), 0);
 
     for a in -11..11 {
         for b in -11..11 {
@@ -71,21 +85,39 @@ fn main() {
                 if choose_material < 0.8 {
                     // Diffuse
                     let albedo = Texture::solid(rng.random_color() * rng.random_color());
+                    let end = center + Vec3::new(0., rng.gen_range(0. ..0.5), 0.);
 
-                    spheres.push(Sphere::new(center, 0.2, Material::Diffuse { albedo }));
+                    spheres.push(Sphere::moving(
+                        center,
+                        end,
+                        0.2,
+                        Material::Diffuse { albedo },
+                    ));
                 } else if choose_material < 0.95 {
                     // Metal
                     let mut random = || rng.gen_range(0.5..1.);
                     let albedo = Texture::solid(Rgb::new(random(), random(), random()));
-                    let fuzz = rng.gen_range(0. ..0.5);
+                    let roughness = rng.gen_range(0. ..0.5);
 
-                    spheres.push(Sphere::new(center, 0.2, Material::Metal { albedo, fuzz }));
+                    spheres.push(Sphere::new(
+                        center,
+                        0.2,
+                        Material::Metal {
+                            albedo,
+                            roughness,
+                            anisotropy: 0.,
+                        },
+                    ));
                 } else {
                     spheres.push(Sphere::new(
                         center,
                         0.2,
                         Material::Glass {
                             refraction_index: 1.5,
+                            roughness: 0.,
+                            tint: Rgb::white(),
+                            absorption: Rgb::zero(),
+                            dispersion: None,
                         },
                     ));
                 }
@@ -93,11 +125,12 @@ fn main() {
         }
     }
 
-    let scene = Scene {
+    let mut scene = Scene {
         camera,
-        spheres,
+        background: Background::Color(Rgb::new(0.7, 0.8, 1.)),
         ..Default::default()
     };
-    let image = render_image(scene);
-    image.save("image.png").unwrap();
+    scene.extend(spheres);
+    let image = render_image(scene, settings);
+    save_image(&image, "image.png", &DisplaySettings::default()).unwrap();
 }
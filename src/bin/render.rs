@@ -0,0 +1,117 @@
+use clap::Parser;
+use raytracer::accumulation::{scene_hash, Accumulation};
+use raytracer::image_io::save_image;
+use raytracer::render_progressive;
+use raytracer::scene_file::load_scene;
+use raytracer::settings::RenderSettings;
+use raytracer::tone_mapping::DisplaySettings;
+use std::fs;
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+/// Renders a scene file (.toml, .json or .ron)
+#[derive(Debug, Parser)]
+struct Arguments {
+    /// The scene file to render
+    scene: PathBuf,
+
+    /// Where to save the image. `.exr`, `.hdr` and `.pfm` keep the full linear range, other
+    /// formats are clamped to 8 bits. Progressive renders update it after every pass
+    #[arg(short, long, default_value = "image.png")]
+    output: PathBuf,
+
+    /// Where to save the accumulated samples after every pass, to resume the render later
+    #[arg(long)]
+    checkpoint: Option<PathBuf>,
+
+    /// Continue from the samples in the checkpoint file
+    #[arg(long, requires = "checkpoint")]
+    resume: bool,
+
+    /// Where to save a heat map of the samples each pixel got, for adaptive renders
+    #[arg(long)]
+    sample_map: Option<PathBuf>,
+
+    #[command(flatten)]
+    settings: RenderSettings,
+
+    #[command(flatten)]
+    display: DisplaySettings,
+}
+
+fn main() -> ExitCode {
+    let Arguments {
+        scene,
+        output,
+        checkpoint,
+        resume,
+        sample_map,
+        settings,
+        display,
+    } = Arguments::parse();
+
+    let scene_hash = match fs::read(&scene) {
+        Ok(contents) => scene_hash(&contents),
+        Err(error) => {
+            eprintln!("Error: failed to read {}: {error}", scene.display());
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let scene = match load_scene(&scene) {
+        Ok(scene) => scene,
+        Err(error) => {
+            eprintln!("Error: {error}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let accumulation = match &checkpoint {
+        Some(checkpoint) if resume => match Accumulation::load(checkpoint) {
+            Ok(accumulation) => match accumulation.resume_mismatch(&settings, scene_hash) {
+                None => {
+                    eprintln!("Resuming from {} passes", accumulation.passes);
+                    accumulation
+                }
+                Some(mismatch) => {
+                    eprintln!(
+                        "Error: can't resume from {}, {mismatch}",
+                        checkpoint.display()
+                    );
+                    return ExitCode::FAILURE;
+                }
+            },
+            Err(error) => {
+                eprintln!("Error: failed to load {}: {error}", checkpoint.display());
+                return ExitCode::FAILURE;
+            }
+        },
+        _ => Accumulation::new(&settings, scene_hash),
+    };
+
+    let accumulation = render_progressive(scene, settings, accumulation, |accumulation| {
+        if let Some(checkpoint) = &checkpoint {
+            if let Err(error) = accumulation.save(checkpoint) {
+                eprintln!("Error: failed to save {}: {error}", checkpoint.display());
+            }
+        }
+
+        if let Err(error) = save_image(&accumulation.image(), &output, &display) {
+            eprintln!("Error: failed to save {}: {error}", output.display());
+        }
+    });
+
+    if let Some(sample_map) = &sample_map {
+        if let Err(error) = accumulation.sample_map().save(sample_map) {
+            eprintln!("Error: failed to save {}: {error}", sample_map.display());
+            return ExitCode::FAILURE;
+        }
+    }
+
+    if let Err(error) = save_image(&accumulation.image(), &output, &display) {
+        eprintln!("Error: failed to save {}: {error}", output.display());
+        return ExitCode::FAILURE;
+    }
+
+    ExitCode::SUCCESS
+}
@@ -1,6 +1,8 @@
+use std::fmt::{self, Display};
 use std::mem::swap;
 
-use rand::Rng;
+use clap::ValueEnum;
+use rand::{Rng, RngCore};
 use vek::Vec3;
 
 use crate::data::Ray;
@@ -35,26 +37,73 @@ impl Aabb {
         }
     }
 
+    pub fn translated(self, offset: Vec3<f32>) -> Self {
+        let axes = self.axes.map2(offset, |axis, offset| {
+            Interval::new(axis.min + offset, axis.max + offset)
+        });
+
+        Self { axes }
+    }
+
     pub fn padded(self) -> Self {
         const DELTA: f32 = 0.0001;
 
         let axes = self.axes.map(|axis| {
-            if axis.size() >= DELTA {
+            // Far from the origin a fixed delta gets lost in f32 rounding
+            let magnitude = f32::max(axis.min.abs(), axis.max.abs());
+            let delta = f32::max(DELTA, magnitude * f32::EPSILON * 16.);
+
+            if axis.size() >= delta {
                 axis
             } else {
-                axis.expand(DELTA)
+                axis.expand(delta)
             }
         });
 
         Self { axes }
     }
 
+    pub fn corners(self) -> [Vec3<f32>; 8] {
+        let Vec3 { x, y, z } = self.axes;
+
+        [
+            Vec3::new(x.min, y.min, z.min),
+            Vec3::new(x.min, y.min, z.max),
+            Vec3::new(x.min, y.max, z.min),
+            Vec3::new(x.min, y.max, z.max),
+            Vec3::new(x.max, y.min, z.min),
+            Vec3::new(x.max, y.min, z.max),
+            Vec3::new(x.max, y.max, z.min),
+            Vec3::new(x.max, y.max, z.max),
+        ]
+    }
+
+    pub fn surface_area(self) -> f32 {
+        let size = self.axes.map(|axis| axis.size());
+
+        2. * (size.x * size.y + size.y * size.z + size.z * size.x)
+    }
+
+    pub fn centroid(self) -> Vec3<f32> {
+        self.axes.map(|axis| (axis.min + axis.max) / 2.)
+    }
+
     pub fn ray_hits(self, ray: Ray, interval: Interval) -> bool {
+        self.ray_hits_inverse(ray.origin, ray.direction.map(|d| 1. / d), interval)
+    }
+
+    /// Like [`Aabb::ray_hits`], but with `1 / direction` precomputed so it can be reused
+    pub fn ray_hits_inverse(
+        self,
+        origin: Vec3<f32>,
+        inverse_direction: Vec3<f32>,
+        interval: Interval,
+    ) -> bool {
         let mut interval = interval;
 
         for axis in 0..3 {
-            let inverse_direction = 1. / ray.direction[axis];
-            let origin = ray.origin[axis];
+            let inverse_direction = inverse_direction[axis];
+            let origin = origin[axis];
 
             let mut t0 = (self.axes[axis].min - origin) * inverse_direction;
             let mut t1 = (self.axes[axis].max - origin) * inverse_direction;
@@ -77,11 +126,27 @@ impl Aabb {
 
 impl FromIterator<Aabb> for Option<Aabb> {
     fn from_iter<T: IntoIterator<Item = Aabb>>(iter: T) -> Self {
-        iter.into_iter()
-            .reduce(|acc, bounding_box| Aabb::combine(acc, bounding_box))
+        iter.into_iter().reduce(Aabb::combine)
     }
 }
 
+/// How the objects are split when building a [`BvhNode`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum BvhBuilder {
+    /// Sorts along a random axis and splits at the median
+    Median,
+
+    /// Binned surface area heuristic along the longest axis
+    #[default]
+    Sah,
+}
+
+/// Estimated cost of testing a ray against a bounding box, relative to intersecting an object
+const TRAVERSAL_COST: f32 = 0.125;
+const INTERSECTION_COST: f32 = 1.;
+
+const SAH_BIN_COUNT: usize = 12;
+
 #[derive(Debug)]
 pub enum BvhNode<T> {
     Leaf {
@@ -98,6 +163,13 @@ pub enum BvhNode<T> {
 }
 
 impl<T: Hittable + Clone> BvhNode<T> {
+    pub fn build(objects: &[T], builder: BvhBuilder, rng: &mut impl Rng) -> Option<Self> {
+        match builder {
+            BvhBuilder::Median => Self::new(objects, rng),
+            BvhBuilder::Sah => Self::new_sah(objects),
+        }
+    }
+
     pub fn new(objects: &[T], rng: &mut impl Rng) -> Option<Self> {
         let axis = rng.gen_range(0..=2);
         let compare_bounding_boxes =
@@ -148,17 +220,145 @@ impl<T: Hittable + Clone> BvhNode<T> {
             }
         }
     }
+
+    pub fn new_sah(objects: &[T]) -> Option<Self> {
+        match objects {
+            [] => None,
+
+            [object] => Some(BvhNode::Leaf {
+                bounding_box: object.bounding_box(),
+                left: object.clone(),
+                right: None,
+            }),
+
+            [left, right] => Some(BvhNode::Leaf {
+                bounding_box: Aabb::combine(left.bounding_box(), right.bounding_box()),
+                left: left.clone(),
+                right: Some(right.clone()),
+            }),
+
+            objects => {
+                let (left_objects, right_objects) = split_sah(objects);
+
+                let left = Box::new(BvhNode::new_sah(&left_objects).unwrap());
+                let right = Box::new(BvhNode::new_sah(&right_objects).unwrap());
+
+                Some(BvhNode::Branch {
+                    bounding_box: Aabb::combine(left.bounding_box(), right.bounding_box()),
+                    left,
+                    right,
+                })
+            }
+        }
+    }
+}
+
+/// Splits the objects into two non-empty halves with the lowest estimated traversal cost
+fn split_sah<T: Hittable + Clone>(objects: &[T]) -> (Vec<T>, Vec<T>) {
+    let centroids = objects
+        .iter()
+        .map(|object| object.bounding_box().centroid())
+        .collect::<Vec<_>>();
+
+    let centroid_bounds = centroids
+        .iter()
+        .map(|&centroid| Aabb::from_extremes(centroid, centroid))
+        .collect::<Option<Aabb>>()
+        .unwrap();
+
+    let extent = centroid_bounds.axes.map(|axis| axis.size());
+    let axis = if extent.x >= extent.y && extent.x >= extent.z {
+        0
+    } else if extent.y >= extent.z {
+        1
+    } else {
+        2
+    };
+
+    let axis_bounds = centroid_bounds.axes[axis];
+
+    // All centroids in the same spot, binning can't separate them
+    if axis_bounds.size() <= 0. {
+        let (left, right) = objects.split_at(objects.len() / 2);
+        return (left.to_vec(), right.to_vec());
+    }
+
+    let bin_of = |centroid: Vec3<f32>| {
+        let offset = (centroid[axis] - axis_bounds.min) / axis_bounds.size();
+        ((offset * SAH_BIN_COUNT as f32) as usize).min(SAH_BIN_COUNT - 1)
+    };
+
+    let mut bin_counts = [0; SAH_BIN_COUNT];
+    let mut bin_bounds = [None::<Aabb>; SAH_BIN_COUNT];
+
+    for (object, &centroid) in objects.iter().zip(&centroids) {
+        let bin = bin_of(centroid);
+        let bounding_box = object.bounding_box();
+
+        bin_counts[bin] += 1;
+        bin_bounds[bin] = Some(match bin_bounds[bin] {
+            Some(bounds) => Aabb::combine(bounds, bounding_box),
+            None => bounding_box,
+        });
+    }
+
+    // Sweep from both ends, so the cost of splitting before each bin is known
+    let sweep = |bins: &mut dyn Iterator<Item = usize>| {
+        let mut count = 0;
+        let mut bounds = None::<Aabb>;
+
+        bins.map(|bin| {
+            count += bin_counts[bin];
+            bounds = [bounds, bin_bounds[bin]].into_iter().flatten().collect();
+
+            (count, bounds.map_or(0., Aabb::surface_area))
+        })
+        .collect::<Vec<_>>()
+    };
+
+    let from_left = sweep(&mut (0..SAH_BIN_COUNT));
+    let mut from_right = sweep(&mut (0..SAH_BIN_COUNT).rev());
+    from_right.reverse();
+
+    let best_split = (1..SAH_BIN_COUNT)
+        .filter(|&split| from_left[split - 1].0 != 0 && from_right[split].0 != 0)
+        .min_by(|&a, &b| {
+            let cost = |split: usize| {
+                let (left_count, left_area) = from_left[split - 1];
+                let (right_count, right_area) = from_right[split];
+
+                left_area * left_count as f32 + right_area * right_count as f32
+            };
+
+            cost(a).partial_cmp(&cost(b)).unwrap()
+        });
+
+    let Some(best_split) = best_split else {
+        let (left, right) = objects.split_at(objects.len() / 2);
+        return (left.to_vec(), right.to_vec());
+    };
+
+    let (left, right): (Vec<_>, Vec<_>) = objects
+        .iter()
+        .zip(&centroids)
+        .partition(|(_, &centroid)| bin_of(centroid) < best_split);
+
+    let unzip = |pairs: Vec<(&T, &Vec3<f32>)>| {
+        pairs
+            .into_iter()
+            .map(|(object, _)| object.clone())
+            .collect()
+    };
+
+    (unzip(left), unzip(right))
 }
 
 impl<T: Hittable> Hittable for BvhNode<T> {
     fn bounding_box(&self) -> Aabb {
-        match self {
-            BvhNode::Leaf { bounding_box, .. } => *bounding_box,
-            BvhNode::Branch { bounding_box, .. } => *bounding_box,
-        }
+        self.node_bounding_box()
     }
 
-    fn raycast(&self, ray: Ray, interval: Interval) -> Option<RayHit> {
+    fn raycast(&self, ray: Ray, interval: Interval, rng: &mut dyn RngCore) -> Option<RayHit> {
         match self {
             BvhNode::Leaf {
                 bounding_box,
@@ -169,7 +369,7 @@ impl<T: Hittable> Hittable for BvhNode<T> {
                     return None;
                 }
 
-                let hit_left = left.raycast(ray, interval);
+                let hit_left = left.raycast(ray, interval, rng);
 
                 let interval = if let Some(ray_hit) = &hit_left {
                     Interval::new(interval.min, ray_hit.distance)
@@ -179,7 +379,7 @@ impl<T: Hittable> Hittable for BvhNode<T> {
 
                 let hit_right = right
                     .as_ref()
-                    .and_then(|right| right.raycast(ray, interval));
+                    .and_then(|right| right.raycast(ray, interval, rng));
 
                 // Prioritize hit_right since it will always be closer than hit_left
                 if hit_right.is_some() {
@@ -198,7 +398,7 @@ impl<T: Hittable> Hittable for BvhNode<T> {
                     return None;
                 }
 
-                let hit_left = left.raycast(ray, interval);
+                let hit_left = left.raycast(ray, interval, rng);
 
                 let interval = if let Some(ray_hit) = &hit_left {
                     Interval::new(interval.min, ray_hit.distance)
@@ -206,7 +406,7 @@ impl<T: Hittable> Hittable for BvhNode<T> {
                     interval
                 };
 
-                let hit_right = right.raycast(ray, interval);
+                let hit_right = right.raycast(ray, interval, rng);
 
                 // Prioritize hit_right since it will always be closer than hit_left
                 if hit_right.is_some() {
@@ -218,3 +418,91 @@ impl<T: Hittable> Hittable for BvhNode<T> {
         }
     }
 }
+
+/// Shape of a bvh, used to compare builders
+#[derive(Debug, Clone, Copy)]
+pub struct BvhStats {
+    pub node_count: usize,
+    pub leaf_count: usize,
+    pub object_count: usize,
+    pub max_depth: usize,
+    pub min_leaf_size: usize,
+    pub max_leaf_size: usize,
+
+    /// Expected cost of a ray passing through the root, from the surface area heuristic
+    pub estimated_cost: f32,
+}
+
+impl BvhStats {
+    pub fn average_leaf_size(&self) -> f32 {
+        self.object_count as f32 / self.leaf_count as f32
+    }
+}
+
+impl Display for BvhStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} nodes, {} leaves, depth {}, leaf sizes {}..={} (average {:.2}), estimated cost {:.2}",
+            self.node_count,
+            self.leaf_count,
+            self.max_depth,
+            self.min_leaf_size,
+            self.max_leaf_size,
+            self.average_leaf_size(),
+            self.estimated_cost
+        )
+    }
+}
+
+impl<T> BvhNode<T> {
+    fn node_bounding_box(&self) -> Aabb {
+        match self {
+            BvhNode::Leaf { bounding_box, .. } => *bounding_box,
+            BvhNode::Branch { bounding_box, .. } => *bounding_box,
+        }
+    }
+
+    pub fn stats(&self) -> BvhStats {
+        let mut stats = BvhStats {
+            node_count: 0,
+            leaf_count: 0,
+            object_count: 0,
+            max_depth: 0,
+            min_leaf_size: usize::MAX,
+            max_leaf_size: 0,
+            estimated_cost: 0.,
+        };
+
+        let root_area = self.node_bounding_box().surface_area();
+        self.collect_stats(&mut stats, 1, root_area);
+
+        stats
+    }
+
+    fn collect_stats(&self, stats: &mut BvhStats, depth: usize, root_area: f32) {
+        let area_ratio = self.node_bounding_box().surface_area() / root_area;
+
+        stats.node_count += 1;
+        stats.max_depth = stats.max_depth.max(depth);
+
+        match self {
+            BvhNode::Leaf { right, .. } => {
+                let size = 1 + right.iter().len();
+
+                stats.leaf_count += 1;
+                stats.object_count += size;
+                stats.min_leaf_size = stats.min_leaf_size.min(size);
+                stats.max_leaf_size = stats.max_leaf_size.max(size);
+                stats.estimated_cost += area_ratio * INTERSECTION_COST * size as f32;
+            }
+
+            BvhNode::Branch { left, right, .. } => {
+                stats.estimated_cost += area_ratio * TRAVERSAL_COST;
+
+                left.collect_stats(stats, depth + 1, root_area);
+                right.collect_stats(stats, depth + 1, root_area);
+            }
+        }
+    }
+}
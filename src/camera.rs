@@ -1,4 +1,4 @@
-use vek::{Rgb, Vec2, Vec3};
+use vek::{Vec2, Vec3};
 
 #[derive(Debug, Clone, Default)]
 pub struct Camera {
@@ -6,14 +6,17 @@ pub struct Camera {
     pub target: Vec3<f32>,
     pub up: Vec3<f32>,
 
-    pub background_color: Rgb<f32>,
     pub vertical_fov: f32,
     pub defocus_angle: f32,
     pub focus_distance: f32,
+
+    /// Rays are sent at random times between these, moving shapes get motion blur. Both lie
+    /// within 0..=1, the time moving shapes are bounded for
+    pub shutter_open: f32,
+    pub shutter_close: f32,
 }
 
 pub struct Viewport {
-    pub background_color: Rgb<f32>,
     pub origin: Vec3<f32>,
     pub upper_left_pixel_position: Vec3<f32>,
 
@@ -22,6 +25,9 @@ pub struct Viewport {
 
     pub horizontal_defocus_disk: Vec3<f32>,
     pub vertical_defocus_disk: Vec3<f32>,
+
+    pub shutter_open: f32,
+    pub shutter_close: f32,
 }
 
 pub fn calculate_viewport(camera: Camera, image_size: Vec2<u32>) -> Viewport {
@@ -52,7 +58,6 @@ pub fn calculate_viewport(camera: Camera, image_size: Vec2<u32>) -> Viewport {
     let vertical_defocus_disk = v * defocus_radius;
 
     Viewport {
-        background_color: camera.background_color,
         origin: camera.position,
         upper_left_pixel_position,
 
@@ -61,5 +66,8 @@ pub fn calculate_viewport(camera: Camera, image_size: Vec2<u32>) -> Viewport {
 
         horizontal_defocus_disk,
         vertical_defocus_disk,
+
+        shutter_open: camera.shutter_open,
+        shutter_close: camera.shutter_close,
     }
 }
@@ -1,15 +1,25 @@
 use crate::{bvh::Aabb, interval::Interval, materials::Material};
+use rand::RngCore;
+use std::fmt::Debug;
+use std::sync::Arc;
 use vek::{Rgb, Vec2, Vec3};
 
 #[derive(Debug, Clone, Copy)]
 pub struct Ray {
     pub origin: Vec3<f32>,
     pub direction: Vec3<f32>,
+
+    /// When during the exposure the ray was sent, moving shapes are placed at this time
+    pub time: f32,
 }
 
 impl Ray {
-    pub fn new(origin: Vec3<f32>, direction: Vec3<f32>) -> Self {
-        Self { origin, direction }
+    pub fn new(origin: Vec3<f32>, direction: Vec3<f32>, time: f32) -> Self {
+        Self {
+            origin,
+            direction,
+            time,
+        }
     }
 
     pub fn at(self, t: f32) -> Vec3<f32> {
@@ -27,10 +37,55 @@ impl Ray {
     }
 }
 
-pub trait Hittable {
+pub trait Hittable: Debug + Send + Sync {
     fn bounding_box(&self) -> Aabb;
 
-    fn raycast(&self, ray: Ray, interval: Interval) -> Option<RayHit>;
+    /// `rng` is the path's, for shapes that are hit at random like volumes
+    fn raycast(&self, ray: Ray, interval: Interval, rng: &mut dyn RngCore) -> Option<RayHit>;
+
+    /// Whether the integrator should sample this shape directly as a light. Shapes that can
+    /// be lights have to implement `pdf_value` and `sample_direction` too
+    fn is_light(&self) -> bool {
+        false
+    }
+
+    /// Probability density over solid angle of `sample_direction` picking the direction of
+    /// `ray` from its origin. Only asked of lights
+    fn pdf_value(&self, _ray: Ray, _rng: &mut dyn RngCore) -> f32 {
+        unreachable!("{self:?} is sampled as a light but has no pdf_value")
+    }
+
+    /// Random direction from `origin` towards a point on the shape. Only asked of lights
+    fn sample_direction(
+        &self,
+        _origin: Vec3<f32>,
+        _time: f32,
+        _rng: &mut dyn RngCore,
+    ) -> Vec3<f32> {
+        unreachable!("{self:?} is sampled as a light but has no sample_direction")
+    }
+}
+
+impl<T: Hittable + ?Sized> Hittable for Arc<T> {
+    fn bounding_box(&self) -> Aabb {
+        self.as_ref().bounding_box()
+    }
+
+    fn raycast(&self, ray: Ray, interval: Interval, rng: &mut dyn RngCore) -> Option<RayHit> {
+        self.as_ref().raycast(ray, interval, rng)
+    }
+
+    fn is_light(&self) -> bool {
+        self.as_ref().is_light()
+    }
+
+    fn pdf_value(&self, ray: Ray, rng: &mut dyn RngCore) -> f32 {
+        self.as_ref().pdf_value(ray, rng)
+    }
+
+    fn sample_direction(&self, origin: Vec3<f32>, time: f32, rng: &mut dyn RngCore) -> Vec3<f32> {
+        self.as_ref().sample_direction(origin, time, rng)
+    }
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -60,11 +115,83 @@ pub struct RayHit {
     pub material: Material,
 }
 
-#[derive(Debug, Clone)]
-pub struct ScatterResult {
-    /// The new ray
-    pub scattered: Ray,
+/// A direction picked by a material for the next bounce
+#[derive(Debug, Clone, Copy)]
+pub struct BsdfSample {
+    /// Towards where the light comes from, unit length
+    pub direction: Vec3<f32>,
+
+    /// The bsdf times the cosine between the direction and the normal
+    pub value: Rgb<f32>,
+
+    /// Probability density of picking the direction
+    pub pdf: f32,
 
-    /// The color produced from scattering
-    pub attenuation: Rgb<f32>,
+    /// Delta lobes like mirrors, their value and pdf are only meaningful as a ratio and they
+    /// can't be evaluated for other directions
+    pub is_specular: bool,
+}
+
+impl BsdfSample {
+    /// A delta lobe scattering towards `direction`, tinted by `attenuation`
+    pub fn specular(direction: Vec3<f32>, attenuation: Rgb<f32>) -> Self {
+        Self {
+            direction: direction.normalized(),
+            value: attenuation,
+            pdf: 1.,
+            is_specular: true,
+        }
+    }
+}
+
+/// Orthonormal basis around `w`
+#[derive(Debug, Clone, Copy)]
+pub struct Onb {
+    pub u: Vec3<f32>,
+    pub v: Vec3<f32>,
+    pub w: Vec3<f32>,
+}
+
+impl Onb {
+    pub fn new(w: Vec3<f32>) -> Self {
+        let w = w.normalized();
+
+        // Any vector not parallel to w works for building the basis
+        let helper = if w.x.abs() > 0.9 {
+            Vec3::unit_y()
+        } else {
+            Vec3::unit_x()
+        };
+
+        let u = w.cross(helper).normalized();
+        let v = w.cross(u);
+
+        Self { u, v, w }
+    }
+
+    /// Basis with `u` perpendicular to `reference`, so it varies smoothly over a surface
+    /// instead of jumping like with `new`. Falls back to `new` when `w` is along `reference`
+    pub fn with_reference(w: Vec3<f32>, reference: Vec3<f32>) -> Self {
+        let w = w.normalized();
+        let u = reference.cross(w);
+
+        if u.magnitude_squared() < 1e-6 {
+            return Self::new(w);
+        }
+
+        let u = u.normalized();
+        let v = w.cross(u);
+
+        Self { u, v, w }
+    }
+
+    /// From world space to coordinates in this basis
+    pub fn to_local(self, world: Vec3<f32>) -> Vec3<f32> {
+        Vec3::new(world.dot(self.u), world.dot(self.v), world.dot(self.w))
+    }
+
+    /// From coordinates in this basis to world space
+    pub fn to_world(self, local: Vec3<f32>) -> Vec3<f32> {
+        self.u * local.x + self.v * local.y + self.w * local.z
+    }
 }
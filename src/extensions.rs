@@ -1,4 +1,5 @@
 use rand::Rng;
+use std::f32::consts::PI;
 use vek::{Rgb, Vec2, Vec3};
 
 pub trait RngExtension: Rng {
@@ -40,9 +41,20 @@ pub trait RngExtension: Rng {
         }
     }
 
+    /// Direction in the hemisphere around z, distributed by the cosine of its angle to z
+    fn random_cosine_direction(&mut self) -> Vec3<f32> {
+        let r1 = self.gen::<f32>();
+        let r2 = self.gen::<f32>();
+
+        let phi = 2. * PI * r1;
+        let radius = r2.sqrt();
+
+        Vec3::new(phi.cos() * radius, phi.sin() * radius, f32::sqrt(1. - r2))
+    }
+
     fn random_color(&mut self) -> Rgb<f32> {
         Rgb::new(self.gen(), self.gen(), self.gen())
     }
 }
 
-impl<T: Rng> RngExtension for T {}
+impl<T: Rng + ?Sized> RngExtension for T {}
@@ -0,0 +1,148 @@
+use crate::tone_mapping::{srgb_eotf, DisplaySettings};
+use image::codecs::hdr::{HdrDecoder, HdrEncoder};
+use image::{DynamicImage, ImageError, Rgb32FImage, RgbImage};
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Write};
+use std::path::Path;
+use vek::Rgb;
+
+/// What the values of an 8 or 16 bit image mean. Floating point images are always linear
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ColorSpace {
+    /// sRGB encoded colors, like photos and albedo maps
+    Srgb,
+
+    /// Data that is used as is, like roughness and metallic maps
+    Linear,
+}
+
+/// Opens any image as linear floats. The image crate only gives 8 bits for Radiance
+/// `.hdr` files, so those are decoded separately to keep values above 1
+pub fn open_image(
+    path: impl AsRef<Path>,
+    color_space: ColorSpace,
+) -> Result<Rgb32FImage, ImageError> {
+    let path = path.as_ref();
+
+    if !has_extension(path, "hdr") {
+        return Ok(decode(image::open(path)?, color_space));
+    }
+
+    let decoder = HdrDecoder::new(BufReader::new(File::open(path)?))?;
+    let metadata = decoder.metadata();
+
+    let pixels = decoder
+        .read_image_hdr()?
+        .into_iter()
+        .flat_map(|pixel| pixel.0)
+        .collect();
+
+    Ok(Rgb32FImage::from_raw(metadata.width, metadata.height, pixels).unwrap())
+}
+
+/// To linear floats, undoing the sRGB encoding of integer images in `ColorSpace::Srgb`
+fn decode(image: DynamicImage, color_space: ColorSpace) -> Rgb32FImage {
+    let is_float = matches!(
+        image,
+        DynamicImage::ImageRgb32F(_) | DynamicImage::ImageRgba32F(_)
+    );
+
+    let mut image = image.into_rgb32f();
+
+    if color_space == ColorSpace::Srgb && !is_float {
+        for pixel in image.pixels_mut() {
+            pixel.0 = pixel.0.map(srgb_eotf);
+        }
+    }
+
+    image
+}
+
+fn has_extension(path: &Path, extension: &str) -> bool {
+    path.extension()
+        .is_some_and(|path_extension| path_extension.eq_ignore_ascii_case(extension))
+}
+
+/// Saves the linear framebuffer, untouched in floating point for `.exr`, `.hdr` and `.pfm`
+/// and through `display` to 8 bits for any other format the image crate can write
+pub fn save_image(
+    image: &Rgb32FImage,
+    path: impl AsRef<Path>,
+    display: &DisplaySettings,
+) -> Result<(), ImageError> {
+    let path = path.as_ref();
+
+    if has_extension(path, "exr") {
+        DynamicImage::ImageRgb32F(image.clone()).save(path)
+    } else if has_extension(path, "hdr") {
+        save_hdr(image, path)
+    } else if has_extension(path, "pfm") {
+        save_pfm(image, path).map_err(ImageError::IoError)
+    } else {
+        to_rgb8(image, display).save(path)
+    }
+}
+
+pub fn to_rgb8(image: &Rgb32FImage, display: &DisplaySettings) -> RgbImage {
+    let transform = display.transform();
+
+    RgbImage::from_fn(image.width(), image.height(), |x, y| {
+        let color = transform.apply(Rgb::from(image.get_pixel(x, y).0));
+
+        image::Rgb(color.map(|c| (c * 255.).round() as u8).into_array())
+    })
+}
+
+/// Radiance RGBE
+fn save_hdr(image: &Rgb32FImage, path: &Path) -> Result<(), ImageError> {
+    let file = BufWriter::new(File::create(path)?);
+    let pixels = image.pixels().copied().collect::<Vec<_>>();
+
+    HdrEncoder::new(file).encode(&pixels, image.width() as usize, image.height() as usize)
+}
+
+/// Portable float map, little endian with the bottom row first
+fn save_pfm(image: &Rgb32FImage, path: &Path) -> io::Result<()> {
+    let mut file = BufWriter::new(File::create(path)?);
+
+    // A negative scale means little endian
+    write!(file, "PF\n{} {}\n-1.0\n", image.width(), image.height())?;
+
+    for row in image.rows().rev() {
+        for pixel in row {
+            for channel in pixel.0 {
+                file.write_all(&channel.to_le_bytes())?;
+            }
+        }
+    }
+
+    file.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn srgb_images_are_decoded_to_linear() {
+        let path = std::env::temp_dir().join("raytracer_image_io_mid_grey.png");
+        RgbImage::from_pixel(1, 1, image::Rgb([128; 3]))
+            .save(&path)
+            .unwrap();
+
+        let color = open_image(&path, ColorSpace::Srgb)
+            .unwrap()
+            .get_pixel(0, 0)
+            .0;
+        assert!(
+            color.iter().all(|&c| (c - 0.214).abs() < 0.003),
+            "{color:?}"
+        );
+
+        let data = open_image(&path, ColorSpace::Linear)
+            .unwrap()
+            .get_pixel(0, 0)
+            .0;
+        assert_eq!(data, [128. / 255.; 3]);
+    }
+}
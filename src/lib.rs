@@ -1,119 +1,395 @@
+pub mod accumulation;
+pub mod background;
 pub mod bvh;
 pub mod camera;
 pub mod data;
 pub mod extensions;
+pub mod image_io;
 pub mod interval;
+pub mod lights;
+pub mod linear_bvh;
 pub mod materials;
+pub mod obj;
+pub mod scene_file;
+pub mod settings;
 pub mod shapes;
+pub mod sky;
 pub mod texture;
+pub mod tiles;
+pub mod tone_mapping;
 
+use crate::accumulation::{Accumulation, PixelStats};
+use crate::background::Background;
 use crate::camera::Camera;
-use crate::data::{Ray, RayHit};
+use crate::data::{Face, Ray, RayHit};
 use crate::extensions::RngExtension;
-use crate::shapes::sphere::Sphere;
-use crate::{bvh::BvhNode, camera::calculate_viewport};
+use crate::lights::Light;
+use crate::materials::{sample_wavelength, transmittance};
+use crate::tiles::{tiles, Tile, TileQueue};
+use crate::{
+    bvh::{BvhBuilder, BvhNode, BvhStats},
+    camera::{calculate_viewport, Viewport},
+};
 use bvh::Aabb;
-use data::{Hittable, ScatterResult};
-use image::RgbImage;
-use indicatif::{ParallelProgressIterator, ProgressStyle};
+use data::Hittable;
+use image::Rgb32FImage;
+use indicatif::{ProgressBar, ProgressStyle};
 use interval::Interval;
-use rand::thread_rng;
-use rand::{rngs::SmallRng, Rng, SeedableRng};
-use rayon::iter::{IntoParallelIterator, ParallelIterator};
-use shapes::quad::Quad;
+use linear_bvh::LinearBvh;
+use rand::{Rng, RngCore};
+use rayon::ThreadPoolBuilder;
+use settings::{stream_rng, RenderSettings};
+use std::sync::Arc;
 use std::time::Instant;
-use vek::{Rgb, Vec2};
+use vek::{Rgb, Vec2, Vec3};
 
 #[derive(Debug, Clone, Default)]
 pub struct Scene {
     pub camera: Camera,
-    pub spheres: Vec<Sphere>,
-    pub quads: Vec<Quad>,
+    pub objects: Vec<Arc<dyn Hittable>>,
+    pub lights: Vec<Light>,
+    pub background: Background,
 }
 
+impl Scene {
+    pub fn add(&mut self, object: impl Hittable + 'static) {
+        self.objects.push(Arc::new(object));
+    }
+}
+
+impl<T: Hittable + 'static> Extend<T> for Scene {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, objects: I) {
+        for object in objects {
+            self.add(object);
+        }
+    }
+}
+
+#[derive(Debug)]
 pub struct World {
-    pub spheres: Option<BvhNode<Sphere>>,
-    pub quads: Option<BvhNode<Quad>>,
+    /// None when the scene has no shapes, only a background and delta lights
+    pub bvh: Option<LinearBvh<Arc<dyn Hittable>>>,
+    pub bvh_stats: Option<BvhStats>,
+
+    /// Emissive shapes sampled directly by the integrator
+    pub area_lights: Vec<Arc<dyn Hittable>>,
+
+    pub lights: Vec<Light>,
 
-    pub bounding_box: Aabb,
+    /// Sampled like the area lights when it's an environment map
+    pub background: Background,
 }
 
 impl World {
-    pub fn new(scene: &Scene) -> Self {
-        let spheres = BvhNode::new(&scene.spheres, &mut thread_rng());
-        let quads = BvhNode::new(&scene.quads, &mut thread_rng());
-
-        let bounding_box = [
-            spheres.as_ref().map(|spheres| spheres.bounding_box()),
-            quads.as_ref().map(|quads| quads.bounding_box()),
-        ]
-        .into_iter()
-        .flatten()
-        .collect::<Option<Aabb>>()
-        .expect("Empty scene");
+    pub fn new(scene: &Scene, builder: BvhBuilder, rng: &mut impl Rng) -> Self {
+        let bvh = BvhNode::build(&scene.objects, builder, rng);
+        let bvh_stats = bvh.as_ref().map(BvhNode::stats);
+
+        let area_lights = scene
+            .objects
+            .iter()
+            .filter(|object| object.is_light())
+            .cloned()
+            .collect();
 
         Self {
-            spheres,
-            quads,
-            bounding_box,
+            bvh: bvh.map(LinearBvh::from),
+            bvh_stats,
+            area_lights,
+            lights: scene.lights.clone(),
+            background: scene.background.clone(),
+        }
+    }
+
+    /// Area lights, plus the background when it's sampled
+    fn light_count(&self) -> usize {
+        self.area_lights.len() + self.background.is_sampled() as usize
+    }
+
+    /// Probability density of `sample_light` picking the direction of `ray`
+    pub fn light_pdf(&self, ray: Ray, rng: &mut dyn RngCore) -> f32 {
+        let light_count = self.light_count();
+
+        if light_count == 0 {
+            return 0.;
+        }
+
+        let mut pdf_sum = self
+            .area_lights
+            .iter()
+            .map(|light| light.pdf_value(ray, rng))
+            .sum::<f32>();
+
+        if self.background.is_sampled() {
+            pdf_sum += self.background.pdf(ray.direction.normalized());
+        }
+
+        pdf_sum / light_count as f32
+    }
+
+    /// Direction from `origin` towards a random point on a random area light, or towards
+    /// the background
+    pub fn sample_light(
+        &self,
+        origin: Vec3<f32>,
+        time: f32,
+        rng: &mut impl Rng,
+    ) -> Option<Vec3<f32>> {
+        let light_count = self.light_count();
+
+        if light_count == 0 {
+            return None;
+        }
+
+        let index = rng.gen_range(0..light_count);
+
+        match self.area_lights.get(index) {
+            Some(light) => Some(light.sample_direction(origin, time, rng)),
+            None => Some(self.background.sample_direction(rng)),
         }
     }
 }
 
 impl Hittable for World {
     fn bounding_box(&self) -> Aabb {
-        self.bounding_box
+        match &self.bvh {
+            Some(bvh) => bvh.bounding_box(),
+            None => Aabb::from_extremes(Vec3::zero(), Vec3::zero()),
+        }
+    }
+
+    fn raycast(&self, ray: Ray, interval: Interval, rng: &mut dyn RngCore) -> Option<RayHit> {
+        self.bvh.as_ref()?.raycast(ray, interval, rng)
     }
+}
+
+/// Multiple importance sampling weight of a sample taken with density `pdf`, when
+/// `other_pdf` is the density of the other strategy for the same direction
+fn power_heuristic(pdf: f32, other_pdf: f32) -> f32 {
+    let pdf = pdf * pdf;
+    let other_pdf = other_pdf * other_pdf;
+
+    pdf / (pdf + other_pdf)
+}
 
-    fn raycast(&self, ray: Ray, interval: Interval) -> Option<RayHit> {
-        let raycasts = [
-            self.spheres
-                .as_ref()
-                .and_then(|spheres| spheres.raycast(ray, interval)),
-            self.quads
-                .as_ref()
-                .and_then(|quads| quads.raycast(ray, interval)),
-        ];
-
-        let closest = raycasts
-            .into_iter()
-            .flatten()
-            .min_by(|a, b| a.distance.partial_cmp(&b.distance).unwrap());
-
-        closest
+/// Absorption of the medium a ray leaving `ray_hit` towards `direction` goes through. `media`
+/// are the ones the path that got there is inside, innermost last
+fn medium_towards(media: &[Rgb<f32>], ray_hit: &RayHit, direction: Vec3<f32>) -> Option<Rgb<f32>> {
+    // The normal faces the side the path came from
+    let crosses_surface = ray_hit.normal.dot(direction) < 0.;
+
+    match (crosses_surface, ray_hit.material.absorption(), ray_hit.face) {
+        (true, Some(absorption), Face::Front) => Some(absorption),
+        (true, Some(_), Face::Back) => media.iter().rev().nth(1).copied(),
+        _ => media.last().copied(),
     }
 }
 
-fn ray_color(
+/// Light left after going `distance` through a medium with `absorption`, if any
+fn medium_transmittance(absorption: Option<Rgb<f32>>, distance: f32) -> Rgb<f32> {
+    absorption.map_or(Rgb::one(), |absorption| transmittance(absorption, distance))
+}
+
+/// Light arriving at a non specular hit from a randomly picked light, weighed against
+/// finding the same light by sampling the bsdf
+fn sample_lights(
     ray: Ray,
+    ray_hit: &RayHit,
     world: &World,
-    depth_left: u32,
-    background_color: Rgb<f32>,
+    media: &[Rgb<f32>],
+    wavelength: Option<f32>,
     rng: &mut impl Rng,
 ) -> Rgb<f32> {
-    if depth_left == 0 {
+    let Some(direction) = world.sample_light(ray_hit.point, ray.time, rng) else {
+        return Rgb::zero();
+    };
+
+    let shadow_ray = Ray::new(ray_hit.point, direction, ray.time);
+
+    let incoming = direction.normalized();
+    let outgoing = -ray.direction.normalized();
+
+    let bsdf_pdf = ray_hit
+        .material
+        .pdf(ray_hit, incoming, outgoing, wavelength);
+    let light_pdf = world.light_pdf(shadow_ray, rng);
+
+    if bsdf_pdf <= 0. || light_pdf <= 0. {
         return Rgb::zero();
     }
 
     let interval = Interval::new(0.001, f32::INFINITY);
 
-    if let Some(ray_hit) = world.raycast(ray, interval) {
-        let emission_color = ray_hit.material.emit(ray_hit.uv, ray_hit.point);
+    let medium = medium_towards(media, ray_hit, direction);
+
+    // Whatever is hit first counts, same as when sampling the bsdf, so occluders add nothing
+    let emission_color = match world.raycast(shadow_ray, interval, rng) {
+        Some(light_hit) => {
+            let distance = light_hit.distance * direction.magnitude();
+
+            light_hit.material.emit(&light_hit, -incoming) * medium_transmittance(medium, distance)
+        }
+        None => world.background.radiance(incoming) * medium_transmittance(medium, f32::INFINITY),
+    };
+    let weight = power_heuristic(light_pdf, bsdf_pdf);
+
+    let bsdf = ray_hit
+        .material
+        .eval(ray_hit, incoming, outgoing, wavelength);
+
+    bsdf * emission_color * (weight / light_pdf)
+}
+
+/// Light arriving at a non specular hit from every point and directional light. These
+/// can't be found by sampling the bsdf, so there's nothing to weigh them against
+fn sample_delta_lights(
+    ray: Ray,
+    ray_hit: &RayHit,
+    world: &World,
+    media: &[Rgb<f32>],
+    wavelength: Option<f32>,
+    rng: &mut impl Rng,
+) -> Rgb<f32> {
+    let outgoing = -ray.direction.normalized();
+    let mut color = Rgb::zero();
+
+    for light in &world.lights {
+        let sample = light.sample(ray_hit.point);
+
+        if sample.radiance == Rgb::zero() {
+            continue;
+        }
+
+        let bsdf = ray_hit
+            .material
+            .eval(ray_hit, sample.direction, outgoing, wavelength);
+
+        if bsdf == Rgb::zero() {
+            continue;
+        }
+
+        let shadow_ray = Ray::new(ray_hit.point, sample.direction, ray.time);
+        let interval = Interval::new(0.001, sample.distance - 0.001);
+
+        if world.raycast(shadow_ray, interval, rng).is_none() {
+            let medium = medium_towards(media, ray_hit, sample.direction);
+
+            color += bsdf * sample.radiance * medium_transmittance(medium, sample.distance);
+        }
+    }
+
+    color
+}
+
+/// Bounces before paths start getting terminated at random
+const RUSSIAN_ROULETTE_DEPTH: u32 = 3;
 
-        if let Some(scatter_result) = ray_hit.material.scatter(ray, &ray_hit, rng) {
-            let ScatterResult {
-                scattered,
-                attenuation,
-            } = scatter_result;
+/// Paths survive Russian roulette at most this often, so bright paths still end eventually
+const MAX_SURVIVAL_PROBABILITY: f32 = 0.95;
 
-            attenuation * ray_color(scattered, world, depth_left - 1, background_color, rng)
+/// Follows a path for at most `max_depth` bounces
+fn ray_color(ray: Ray, world: &World, max_depth: u32, rng: &mut impl Rng) -> Rgb<f32> {
+    let interval = Interval::new(0.001, f32::INFINITY);
+
+    let mut ray = ray;
+    let mut color = Rgb::zero();
+
+    // How much of the light found at the current hit reaches the camera
+    let mut throughput = Rgb::one();
+
+    // The density the previous bounce picked the direction of `ray` with, to weigh lights
+    // found this way against light sampling. It's `None` for camera rays and after specular
+    // bounces, which don't sample lights
+    let mut bsdf_pdf = None;
+
+    // Picked the first time the path goes through dispersive glass, from then on it only
+    // carries that wavelength
+    let mut wavelength = None;
+
+    // Absorption of the media the path is inside, innermost last. Kept for the whole path,
+    // so absorption also applies when it leaves through another object
+    let mut media = Vec::new();
+
+    for depth in 0..max_depth {
+        let Some(ray_hit) = world.raycast(ray, interval, rng) else {
+            // Didn't hit anything
+            let weight = match bsdf_pdf {
+                Some(bsdf_pdf) => power_heuristic(bsdf_pdf, world.light_pdf(ray, rng)),
+                None => 1.,
+            };
+
+            throughput *= medium_transmittance(media.last().copied(), f32::INFINITY);
+            color += throughput * world.background.radiance(ray.direction.normalized()) * weight;
+            break;
+        };
+
+        let distance = ray_hit.distance * ray.direction.magnitude();
+        throughput *= medium_transmittance(media.last().copied(), distance);
+
+        if wavelength.is_none() && ray_hit.material.is_dispersive() {
+            let (sampled_wavelength, weight) = sample_wavelength(rng);
+
+            wavelength = Some(sampled_wavelength);
+            throughput *= weight;
+        }
+
+        let outgoing = -ray.direction.normalized();
+
+        let Some(sample) = ray_hit.material.sample(outgoing, &ray_hit, wavelength, rng) else {
+            let emission_color = ray_hit.material.emit(&ray_hit, outgoing);
+
+            let weight = match bsdf_pdf {
+                Some(bsdf_pdf) => power_heuristic(bsdf_pdf, world.light_pdf(ray, rng)),
+                None => 1.,
+            };
+
+            color += throughput * emission_color * weight;
+            break;
+        };
+
+        if sample.is_specular {
+            bsdf_pdf = None;
         } else {
-            emission_color
+            if sample.pdf <= 0. {
+                break;
+            }
+
+            color += throughput
+                * (sample_lights(ray, &ray_hit, world, &media, wavelength, rng)
+                    + sample_delta_lights(ray, &ray_hit, world, &media, wavelength, rng));
+            bsdf_pdf = Some(sample.pdf);
+        }
+
+        throughput *= sample.value / sample.pdf;
+
+        if depth >= RUSSIAN_ROULETTE_DEPTH {
+            let survival_probability = throughput
+                .reduce_partial_max()
+                .min(MAX_SURVIVAL_PROBABILITY);
+
+            if rng.gen::<f32>() >= survival_probability {
+                break;
+            }
+
+            throughput /= survival_probability;
         }
-    } else {
-        // Didn't hit anything
-        background_color
+
+        // Going into or out of a medium
+        if let Some(absorption) = ray_hit.material.absorption() {
+            if ray_hit.normal.dot(sample.direction) < 0. {
+                match ray_hit.face {
+                    Face::Front => media.push(absorption),
+                    Face::Back => {
+                        media.pop();
+                    }
+                }
+            }
+        }
+
+        ray = Ray::new(ray_hit.point, sample.direction, ray.time);
     }
+
+    color
 }
 
 fn pixel_sample_offset(rng: &mut impl Rng) -> Vec2<f32> {
@@ -124,34 +400,167 @@ fn defocus_sample_offset(rng: &mut impl Rng) -> Vec2<f32> {
     rng.random_in_unit_disk()
 }
 
-pub fn render_image(scene: Scene) -> RgbImage {
-    let image_size = Vec2::<u32>::new(600, 600);
-    let amount_of_samples = 10000;
-    let max_depth = 100;
+/// Linear radiance, see `image_io` for saving it
+pub fn render_image(scene: Scene, settings: RenderSettings) -> Rgb32FImage {
+    let accumulation = Accumulation::new(&settings, 0);
+
+    render_progressive(scene, settings, accumulation, |_| {}).image()
+}
 
-    let world = World::new(&scene);
-    let viewport = calculate_viewport(scene.camera, image_size);
+/// Samples per pass for adaptive renders when `settings.pass_samples` isn't set
+const ADAPTIVE_PASS_SAMPLES: u32 = 16;
+
+/// Below this the variance estimate is too rough to decide that a pixel converged
+const MIN_ADAPTIVE_SAMPLES: u32 = 16;
+
+/// Samples `pixel` gets in the next pass, none once it's done
+fn pass_samples_for(pixel: &PixelStats, settings: RenderSettings, pass_samples: u32) -> u32 {
+    if let Some(threshold) = settings.adaptive_threshold {
+        if pixel.samples >= MIN_ADAPTIVE_SAMPLES && pixel.relative_error() < threshold as f64 {
+            return 0;
+        }
+    }
+
+    settings
+        .samples_per_pixel
+        .saturating_sub(pixel.samples)
+        .min(pass_samples)
+}
+
+/// Adds passes of `settings.pass_samples` samples per pixel to `accumulation` until every
+/// pixel of the region has `settings.samples_per_pixel` or, for adaptive renders, converged.
+/// `on_pass` is called after each pass. `accumulation` is either new or from an earlier
+/// render of the same scene at the same size
+pub fn render_progressive(
+    scene: Scene,
+    settings: RenderSettings,
+    mut accumulation: Accumulation,
+    mut on_pass: impl FnMut(&Accumulation),
+) -> Accumulation {
+    assert_eq!(accumulation.size, settings.image_size());
+
+    let seed = accumulation.seed;
+    let world = World::new(
+        &scene,
+        settings.bvh_builder,
+        &mut stream_rng(seed, u64::MAX),
+    );
+    match &world.bvh_stats {
+        Some(bvh_stats) => eprintln!("Bvh: {bvh_stats}"),
+        None => eprintln!("Bvh: no shapes"),
+    }
+
+    let viewport = calculate_viewport(scene.camera, settings.image_size());
+
+    let thread_pool = ThreadPoolBuilder::new()
+        .num_threads(settings.thread_count.unwrap_or(0))
+        .build()
+        .unwrap();
+
+    let pass_samples = match (settings.pass_samples, settings.adaptive_threshold) {
+        (Some(pass_samples), _) => pass_samples.max(1),
+        (None, Some(_)) => ADAPTIVE_PASS_SAMPLES,
+        (None, None) => settings.samples_per_pixel.max(1),
+    };
+
+    let region = settings.region();
+    let tiles = tiles(region, settings.tile_size, settings.tile_order);
 
     // Raytracing
     let start_time = Instant::now();
 
-    let rows = (0..image_size.y).into_par_iter().progress_with_style(
-        ProgressStyle::with_template(
-            "[{elapsed} / {eta}] {bar:40.cyan/blue} {pos:>7}/{len:7} {msg}",
+    loop {
+        let width = accumulation.size.x;
+        let samples = accumulation
+            .pixels
+            .iter()
+            .enumerate()
+            .map(|(index, pixel)| {
+                let position = Vec2::new(index as u32 % width, index as u32 / width);
+
+                if region.contains(position) {
+                    pass_samples_for(pixel, settings, pass_samples)
+                } else {
+                    0
+                }
+            })
+            .collect::<Vec<_>>();
+
+        // Tiles that are done are left out of the pass
+        let pass_tiles = tiles
+            .iter()
+            .copied()
+            .filter(|tile| {
+                tile.pixels()
+                    .any(|pixel| samples[(pixel.y * width + pixel.x) as usize] > 0)
+            })
+            .collect::<Vec<_>>();
+
+        if pass_tiles.is_empty() {
+            break;
+        }
+
+        let pass = accumulation.passes;
+
+        let rendered = thread_pool.install(|| {
+            render_pass(
+                &world,
+                &viewport,
+                settings,
+                seed,
+                &pass_tiles,
+                &samples,
+                pass,
+            )
+        });
+
+        accumulation.add_pass(rendered);
+        on_pass(&accumulation);
+    }
+
+    eprintln!("Time taken: {:.2}s", start_time.elapsed().as_secs_f32());
+
+    accumulation
+}
+
+/// Takes `samples[index]` samples for each pixel of `tiles`, the pixels of each tile are row
+/// by row
+fn render_pass(
+    world: &World,
+    viewport: &Viewport,
+    settings: RenderSettings,
+    seed: u64,
+    tiles: &[Tile],
+    samples: &[u32],
+    pass: u32,
+) -> Vec<(Tile, Vec<PixelStats>)> {
+    let image_size = settings.image_size();
+    let active_pixels = samples.iter().filter(|&&samples| samples > 0).count();
+
+    let tiles = TileQueue::new(tiles);
+
+    let progress = ProgressBar::new(tiles.len() as u64)
+        .with_style(
+            ProgressStyle::with_template(
+                "[{elapsed} / {eta}] {bar:40.cyan/blue} {pos:>5}/{len:5} tiles {msg}",
+            )
+            .unwrap(),
         )
-        .unwrap(),
-    );
-    let rows = rows
-        .map(|y| {
-            let mut pixels = Vec::with_capacity(image_size.x as usize);
-            let mut rng = SmallRng::from_entropy();
+        .with_message(format!("pass {}, {active_pixels} pixels", pass + 1));
 
-            for x in 0..image_size.x {
-                let pixel_position = Vec2::new(x, y);
+    let render_tile = |tile: Tile| {
+        let pixels = tile
+            .pixels()
+            .map(|pixel_position| {
+                let mut stats = PixelStats::default();
 
-                let mut color = Rgb::zero();
+                let pixel_index = pixel_position.y * image_size.x + pixel_position.x;
 
-                for _ in 0..amount_of_samples {
+                // Every pass and pixel gets its own stream, so the tiles, their order and
+                // the region don't change the result
+                let mut rng = stream_rng(seed, (pass as u64) << 32 | pixel_index as u64);
+
+                for _ in 0..samples[pixel_index as usize] {
                     let sample_position =
                         pixel_position.as_::<f32>() + pixel_sample_offset(&mut rng);
 
@@ -166,32 +575,32 @@ pub fn render_image(scene: Scene) -> RgbImage {
 
                     let ray_direction = pixel_center - ray_origin;
 
-                    let ray = Ray::new(ray_origin, ray_direction);
-
-                    color += ray_color(ray, &world, max_depth, viewport.background_color, &mut rng);
-                }
+                    let time = viewport.shutter_open
+                        + rng.gen::<f32>() * (viewport.shutter_close - viewport.shutter_open);
 
-                color /= amount_of_samples as f32;
-                color = color.map(|c| c.sqrt()); // map from linear to gamma 2
+                    let ray = Ray::new(ray_origin, ray_direction, time);
 
-                let color = color.map(|c| (c * 255.).round() as u8);
+                    stats.add_sample(ray_color(ray, world, settings.max_depth, &mut rng));
+                }
 
-                pixels.push(color);
-            }
+                stats
+            })
+            .collect();
 
-            pixels
-        })
-        .collect::<Vec<_>>();
+        progress.inc(1);
 
-    let mut image = RgbImage::new(image_size.x, image_size.y);
+        (tile, pixels)
+    };
 
-    for (y, row) in rows.into_iter().enumerate() {
-        for (x, pixel) in row.into_iter().enumerate() {
-            image.put_pixel(x as u32, y as u32, pixel.into_array().into());
-        }
-    }
+    // Every thread takes the next tile when it's done with one, so they are started in the
+    // order of `tiles`. Splitting them up front like a parallel iterator would doesn't
+    let rendered = rayon::broadcast(|_| {
+        std::iter::from_fn(|| tiles.claim())
+            .map(render_tile)
+            .collect::<Vec<_>>()
+    });
 
-    eprintln!("Time taken: {:.2}s", start_time.elapsed().as_secs_f32());
+    progress.finish();
 
-    image
+    rendered.into_iter().flatten().collect()
 }
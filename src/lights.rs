@@ -0,0 +1,111 @@
+use vek::{Rgb, Vec3};
+
+/// Light coming from a single point or direction. Rays can't hit these, so the integrator
+/// samples them directly at every non specular hit
+#[derive(Debug, Clone, Copy)]
+pub enum Light {
+    /// Emits `intensity` equally in every direction
+    Point {
+        position: Vec3<f32>,
+        intensity: Rgb<f32>,
+    },
+
+    /// Point light that only shines inside a cone around `direction`
+    Spot {
+        position: Vec3<f32>,
+        direction: Vec3<f32>,
+        intensity: Rgb<f32>,
+        cone: Cone,
+    },
+
+    /// Infinitely far away, like the sun. `direction` is where the light travels towards
+    Directional {
+        direction: Vec3<f32>,
+        irradiance: Rgb<f32>,
+    },
+}
+
+/// Light arriving at a point from a `Light`
+#[derive(Debug, Clone, Copy)]
+pub struct LightSample {
+    /// Unit length, towards the light
+    pub direction: Vec3<f32>,
+
+    /// To the light, infinite for directional lights
+    pub distance: f32,
+
+    pub radiance: Rgb<f32>,
+}
+
+impl Light {
+    pub fn sample(&self, origin: Vec3<f32>) -> LightSample {
+        match *self {
+            Light::Point {
+                position,
+                intensity,
+            } => point_sample(origin, position, intensity),
+
+            Light::Spot {
+                position,
+                direction,
+                intensity,
+                cone,
+            } => {
+                let sample = point_sample(origin, position, intensity);
+                let cosine = direction.normalized().dot(-sample.direction);
+
+                LightSample {
+                    radiance: sample.radiance * cone.falloff(cosine),
+                    ..sample
+                }
+            }
+
+            Light::Directional {
+                direction,
+                irradiance,
+            } => LightSample {
+                direction: -direction.normalized(),
+                distance: f32::INFINITY,
+                radiance: irradiance,
+            },
+        }
+    }
+}
+
+fn point_sample(origin: Vec3<f32>, position: Vec3<f32>, intensity: Rgb<f32>) -> LightSample {
+    let offset = position - origin;
+    let distance = offset.magnitude();
+
+    LightSample {
+        direction: offset / distance,
+        distance,
+        radiance: intensity / (distance * distance),
+    }
+}
+
+/// Cone of a spotlight, or of an area light aimed along its normal
+#[derive(Debug, Clone, Copy)]
+pub struct Cone {
+    /// From the axis to the edge, in radians
+    pub angle: f32,
+
+    /// Fraction of `angle`, next to the edge, over which the light fades out
+    pub blend: f32,
+}
+
+impl Cone {
+    /// How much of the light leaves at `cosine` to the axis
+    pub fn falloff(self, cosine: f32) -> f32 {
+        let outer = self.angle.cos();
+        let inner = f32::cos(self.angle * (1. - self.blend.clamp(0., 1.)));
+
+        if cosine <= outer {
+            0.
+        } else if cosine >= inner {
+            1.
+        } else {
+            let t = (cosine - outer) / (inner - outer);
+            t * t * (3. - 2. * t)
+        }
+    }
+}
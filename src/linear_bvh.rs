@@ -0,0 +1,229 @@
+use crate::{
+    bvh::{Aabb, BvhNode},
+    data::{Hittable, Ray, RayHit},
+    interval::Interval,
+};
+use rand::RngCore;
+
+#[derive(Debug, Clone, Copy)]
+enum LinearNodeKind {
+    /// `count` objects starting at `first` in the object list
+    Leaf { first: u32, count: u32 },
+
+    /// The first child comes right after this node, the second one is at `second_child`.
+    /// The first child is lower along `axis`, used to visit the closest one first
+    Branch { second_child: u32, axis: u8 },
+}
+
+#[derive(Debug, Clone, Copy)]
+struct LinearNode {
+    bounding_box: Aabb,
+    kind: LinearNodeKind,
+}
+
+/// A bvh flattened into one contiguous list of nodes in depth first order, with the objects
+/// stored separately and referred to by index
+#[derive(Debug)]
+pub struct LinearBvh<T> {
+    nodes: Vec<LinearNode>,
+    objects: Vec<T>,
+
+    /// Deepest path from the root, limits the size of the traversal stack
+    max_depth: usize,
+}
+
+/// Traversal stacks up to this size live on the call stack instead of the heap
+const INLINE_STACK_SIZE: usize = 64;
+
+impl<T: Hittable> LinearBvh<T> {
+    /// In the order of the leaves
+    pub fn objects(&self) -> &[T] {
+        &self.objects
+    }
+
+    /// Calls `f` with every object in a leaf whose bounding box the ray passes through, in no
+    /// particular order
+    pub fn for_each_candidate(&self, ray: Ray, interval: Interval, mut f: impl FnMut(&T)) {
+        let inverse_direction = ray.direction.map(|d| 1. / d);
+        let mut stack = vec![0];
+
+        while let Some(node_index) = stack.pop() {
+            let node = &self.nodes[node_index as usize];
+
+            if !node
+                .bounding_box
+                .ray_hits_inverse(ray.origin, inverse_direction, interval)
+            {
+                continue;
+            }
+
+            match node.kind {
+                LinearNodeKind::Leaf { first, count } => self.objects
+                    [first as usize..(first + count) as usize]
+                    .iter()
+                    .for_each(&mut f),
+
+                LinearNodeKind::Branch { second_child, .. } => {
+                    stack.extend([node_index + 1, second_child])
+                }
+            }
+        }
+    }
+
+    fn flatten(&mut self, node: BvhNode<T>, depth: usize) -> u32 {
+        let index = self.nodes.len() as u32;
+        self.max_depth = self.max_depth.max(depth);
+
+        match node {
+            BvhNode::Leaf {
+                bounding_box,
+                left,
+                right,
+            } => {
+                let first = self.objects.len() as u32;
+
+                self.objects.push(left);
+                self.objects.extend(right);
+
+                let count = self.objects.len() as u32 - first;
+
+                self.nodes.push(LinearNode {
+                    bounding_box,
+                    kind: LinearNodeKind::Leaf { first, count },
+                });
+            }
+
+            BvhNode::Branch {
+                bounding_box,
+                left,
+                right,
+            } => {
+                let separation = right.bounding_box().centroid() - left.bounding_box().centroid();
+                let distance = separation.map(f32::abs);
+
+                let axis = if distance.x >= distance.y && distance.x >= distance.z {
+                    0
+                } else if distance.y >= distance.z {
+                    1
+                } else {
+                    2
+                };
+
+                // Patched once the second child's index is known
+                self.nodes.push(LinearNode {
+                    bounding_box,
+                    kind: LinearNodeKind::Branch {
+                        second_child: 0,
+                        axis,
+                    },
+                });
+
+                // The first child is always the one with the lowest centroid along the axis
+                let (first, second) = if separation[axis as usize] >= 0. {
+                    (left, right)
+                } else {
+                    (right, left)
+                };
+
+                self.flatten(*first, depth + 1);
+                let second_child = self.flatten(*second, depth + 1);
+
+                self.nodes[index as usize].kind = LinearNodeKind::Branch { second_child, axis };
+            }
+        }
+
+        index
+    }
+
+    fn traverse(
+        &self,
+        ray: Ray,
+        interval: Interval,
+        rng: &mut dyn RngCore,
+        stack: &mut [u32],
+    ) -> Option<RayHit> {
+        let inverse_direction = ray.direction.map(|d| 1. / d);
+        let direction_is_negative = ray.direction.map(|d| d < 0.);
+
+        let mut interval = interval;
+        let mut closest = None;
+
+        let mut stack_size = 0;
+        let mut node_index = 0;
+
+        loop {
+            let node = &self.nodes[node_index as usize];
+
+            if node
+                .bounding_box
+                .ray_hits_inverse(ray.origin, inverse_direction, interval)
+            {
+                match node.kind {
+                    LinearNodeKind::Leaf { first, count } => {
+                        let objects = &self.objects[first as usize..(first + count) as usize];
+
+                        for object in objects {
+                            if let Some(ray_hit) = object.raycast(ray, interval, rng) {
+                                interval.max = ray_hit.distance;
+                                closest = Some(ray_hit);
+                            }
+                        }
+                    }
+
+                    LinearNodeKind::Branch { second_child, axis } => {
+                        // Visit the child closest to the ray origin first, so hits there can
+                        // cull the other one
+                        let (near, far) = if direction_is_negative[axis as usize] {
+                            (second_child, node_index + 1)
+                        } else {
+                            (node_index + 1, second_child)
+                        };
+
+                        stack[stack_size] = far;
+                        stack_size += 1;
+                        node_index = near;
+
+                        continue;
+                    }
+                }
+            }
+
+            if stack_size == 0 {
+                break;
+            }
+
+            stack_size -= 1;
+            node_index = stack[stack_size];
+        }
+
+        closest
+    }
+}
+
+impl<T: Hittable> From<BvhNode<T>> for LinearBvh<T> {
+    fn from(root: BvhNode<T>) -> Self {
+        let mut bvh = Self {
+            nodes: Vec::new(),
+            objects: Vec::new(),
+            max_depth: 0,
+        };
+
+        bvh.flatten(root, 1);
+
+        bvh
+    }
+}
+
+impl<T: Hittable> Hittable for LinearBvh<T> {
+    fn bounding_box(&self) -> Aabb {
+        self.nodes[0].bounding_box
+    }
+
+    fn raycast(&self, ray: Ray, interval: Interval, rng: &mut dyn RngCore) -> Option<RayHit> {
+        if self.max_depth <= INLINE_STACK_SIZE {
+            self.traverse(ray, interval, rng, &mut [0; INLINE_STACK_SIZE])
+        } else {
+            self.traverse(ray, interval, rng, &mut vec![0; self.max_depth])
+        }
+    }
+}
@@ -1,22 +1,29 @@
-use crate::data::{Ray, RayHit, ScatterResult};
+use crate::data::{BsdfSample, Onb, RayHit};
 use crate::extensions::RngExtension;
 use crate::texture::Texture;
 use rand::Rng;
+use std::f32::consts::PI;
 use std::option::Option;
+use vek::{Rgb, Vec3};
 
-pub fn scatter(albedo: &Texture, ray_hit: &RayHit, rng: &mut impl Rng) -> Option<ScatterResult> {
-    let mut scatter_direction = ray_hit.normal + rng.random_unit_vector();
+pub fn sample(albedo: &Texture, ray_hit: &RayHit, rng: &mut impl Rng) -> Option<BsdfSample> {
+    let direction = Onb::new(ray_hit.normal).to_world(rng.random_cosine_direction());
 
-    // Catch degenerate scatter direction
-    if scatter_direction.is_approx_zero() {
-        scatter_direction = ray_hit.normal;
-    }
+    Some(BsdfSample {
+        direction,
+        value: eval(albedo, ray_hit, direction),
+        pdf: pdf(ray_hit, direction),
+        is_specular: false,
+    })
+}
 
-    let scattered = Ray::new(ray_hit.point, scatter_direction);
-    let attenuation = albedo.color_at(ray_hit.uv, ray_hit.point);
+pub fn eval(albedo: &Texture, ray_hit: &RayHit, direction: Vec3<f32>) -> Rgb<f32> {
+    albedo.color_at(ray_hit.uv, ray_hit.point) * pdf(ray_hit, direction)
+}
 
-    Some(ScatterResult {
-        scattered,
-        attenuation,
-    })
+/// Cosine weighted, which is what `sample` picks
+pub fn pdf(ray_hit: &RayHit, direction: Vec3<f32>) -> f32 {
+    let cosine = ray_hit.normal.dot(direction);
+
+    f32::max(cosine, 0.) / PI
 }
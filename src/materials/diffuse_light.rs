@@ -1,6 +1,32 @@
+use crate::data::{Face, RayHit};
+use crate::lights::Cone;
 use crate::texture::Texture;
-use vek::{Rgb, Vec2, Vec3};
+use vek::{Rgb, Vec3};
 
-pub fn emit(strength: &Texture, uv: Vec2<f32>, point: Vec3<f32>) -> Rgb<f32> {
-    strength.color_at(uv, point)
+pub fn emit(
+    strength: &Texture,
+    two_sided: bool,
+    cosine_power: f32,
+    cone: Option<Cone>,
+    ray_hit: &RayHit,
+    outgoing: Vec3<f32>,
+) -> Rgb<f32> {
+    if !two_sided && matches!(ray_hit.face, Face::Back) {
+        return Rgb::zero();
+    }
+
+    // The normal is on the side that was hit
+    let cosine = ray_hit.normal.dot(outgoing).max(0.);
+
+    let mut scale = 1.;
+
+    if cosine_power != 0. {
+        scale *= cosine.powf(cosine_power);
+    }
+
+    if let Some(cone) = cone {
+        scale *= cone.falloff(cosine);
+    }
+
+    strength.color_at(ray_hit.uv, ray_hit.point) * scale
 }
@@ -1,7 +1,79 @@
-use crate::data::{Face, Ray, RayHit, ScatterResult};
+use crate::data::{BsdfSample, Face, Onb, RayHit};
+use crate::materials::microfacet::{fresnel_dielectric, Ggx};
 use rand::Rng;
 use std::option::Option;
-use vek::{Rgb, Vec3};
+use vek::{Rgb, Vec2, Vec3};
+
+/// Wavelength dependent index of refraction, wavelengths are in micrometers
+#[derive(Debug, Clone, Copy)]
+pub enum Dispersion {
+    /// `n = a + b / λ²`
+    Cauchy { a: f32, b: f32 },
+
+    /// `n² = 1 + Σ bᵢ λ² / (λ² - cᵢ)`
+    Sellmeier { b: [f32; 3], c: [f32; 3] },
+}
+
+impl Dispersion {
+    /// `wavelength` is in nanometers
+    pub fn refraction_index(self, wavelength: f32) -> f32 {
+        let wavelength = wavelength / 1000.;
+        let wavelength_squared = wavelength * wavelength;
+
+        match self {
+            Dispersion::Cauchy { a, b } => a + b / wavelength_squared,
+
+            Dispersion::Sellmeier { b, c } => {
+                let sum = b
+                    .iter()
+                    .zip(c)
+                    .map(|(b, c)| b * wavelength_squared / (wavelength_squared - c))
+                    .sum::<f32>();
+
+                f32::sqrt(1. + sum)
+            }
+        }
+    }
+}
+
+/// Wavelength for a path going through dispersive glass, in nanometers. Each color channel
+/// covers a third of the visible range, the returned weight keeps only the picked channel
+pub fn sample_wavelength(rng: &mut impl Rng) -> (f32, Rgb<f32>) {
+    let channel = rng.gen_range(0..3);
+
+    // Red is the longest
+    let band_start = 600. - 100. * channel as f32;
+    let wavelength = band_start + 100. * rng.gen::<f32>();
+
+    let mut weight = Rgb::zero();
+    weight[channel] = 3.;
+
+    (wavelength, weight)
+}
+
+/// The index of refraction for the wavelength a path carries, if any
+pub fn refraction_index_at(
+    refraction_index: f32,
+    dispersion: Option<Dispersion>,
+    wavelength: Option<f32>,
+) -> f32 {
+    match (dispersion, wavelength) {
+        (Some(dispersion), Some(wavelength)) => dispersion.refraction_index(wavelength),
+        _ => refraction_index,
+    }
+}
+
+/// Beer-Lambert law, for light travelling `distance` inside the glass. `distance` can be
+/// infinite for rays that never leave it
+pub fn transmittance(absorption: Rgb<f32>, distance: f32) -> Rgb<f32> {
+    absorption.map(|absorption| {
+        if absorption > 0. {
+            f32::exp(-absorption * distance)
+        } else {
+            1.
+        }
+    })
+}
 
 fn reflectance(cosine: f32, refraction_ratio: f32) -> f32 {
     let r0 = (1. - refraction_ratio) / (1. + refraction_ratio);
@@ -10,35 +82,228 @@ fn reflectance(cosine: f32, refraction_ratio: f32) -> f32 {
     r0 + (1. - r0) * f32::powi(1. - cosine, 5)
 }
 
-pub fn scatter(
+/// `tint` multiplies light refracted into the glass, once per trip through it
+pub fn sample(
     refraction_index: f32,
-    ray: Ray,
+    tint: Rgb<f32>,
+    ggx: Ggx,
+    outgoing: Vec3<f32>,
     ray_hit: &RayHit,
     rng: &mut impl Rng,
-) -> Option<ScatterResult> {
+) -> Option<BsdfSample> {
+    if !ggx.is_smooth() {
+        return sample_rough(refraction_index, tint, ggx, outgoing, ray_hit, rng);
+    }
+
     let refraction_ratio = match ray_hit.face {
         Face::Front => 1. / refraction_index,
         Face::Back => refraction_index,
     };
 
-    let unit_direction = ray.direction.normalized();
+    let unit_direction = -outgoing;
     let cos_theta = f32::min(Vec3::dot(-unit_direction, ray_hit.normal), 1.);
     let sin_theta = f32::sqrt(1. - cos_theta * cos_theta);
 
     let cannot_refract = (refraction_ratio * sin_theta > 1.)
         || (reflectance(cos_theta, refraction_ratio) > rng.gen());
 
-    let direction = if cannot_refract {
-        unit_direction.reflected(ray_hit.normal)
+    if cannot_refract {
+        let direction = unit_direction.reflected(ray_hit.normal);
+
+        Some(BsdfSample::specular(direction, Rgb::white()))
     } else {
-        unit_direction.refracted(ray_hit.normal, refraction_ratio)
-    };
+        let direction = unit_direction.refracted(ray_hit.normal, refraction_ratio);
+
+        Some(BsdfSample::specular(direction, entry_tint(tint, ray_hit)))
+    }
+}
+
+/// Index of refraction on the other side of the surface relative to the side that was hit
+fn relative_eta(refraction_index: f32, ray_hit: &RayHit) -> f32 {
+    match ray_hit.face {
+        Face::Front => refraction_index,
+        Face::Back => 1. / refraction_index,
+    }
+}
+
+fn refract(outgoing: Vec3<f32>, normal: Vec3<f32>, eta: f32) -> Option<Vec3<f32>> {
+    let cos_o = outgoing.dot(normal);
+    let sin2_t = (1. - cos_o * cos_o) / (eta * eta);
+
+    if sin2_t >= 1. {
+        return None;
+    }
+
+    let cos_t = f32::sqrt(1. - sin2_t);
+
+    Some(-outgoing / eta + normal * (cos_o / eta - cos_t))
+}
+
+/// Walter et al. microfacet model, reflecting or refracting through a sampled visible normal
+fn sample_rough(
+    refraction_index: f32,
+    tint: Rgb<f32>,
+    ggx: Ggx,
+    outgoing: Vec3<f32>,
+    ray_hit: &RayHit,
+    rng: &mut impl Rng,
+) -> Option<BsdfSample> {
+    let eta = relative_eta(refraction_index, ray_hit);
+    let basis = Onb::new(ray_hit.normal);
+    let outgoing = basis.to_local(outgoing);
+
+    if outgoing.z <= 0. {
+        return None;
+    }
+
+    let normal = ggx.sample_visible_normal(outgoing, Vec2::new(rng.gen(), rng.gen()));
+    let fresnel = fresnel_dielectric(outgoing.dot(normal), eta);
+
+    let incoming = if rng.gen::<f32>() < fresnel {
+        Some((-outgoing).reflected(normal)).filter(|incoming| incoming.z > 0.)
+    } else {
+        refract(outgoing, normal, eta).filter(|incoming| incoming.z < 0.)
+    }?;
+
+    let pdf = pdf_local(eta, ggx, incoming, outgoing);
 
-    let scattered = Ray::new(ray_hit.point, direction);
-    let attenuation = Rgb::white();
+    if pdf <= 0. {
+        return None;
+    }
 
-    Some(ScatterResult {
-        scattered,
-        attenuation,
+    Some(BsdfSample {
+        direction: basis.to_world(incoming),
+        value: tinted(
+            eval_local(eta, ggx, incoming, outgoing),
+            entry_tint(tint, ray_hit),
+            incoming,
+        ),
+        pdf,
+        is_specular: false,
     })
 }
+
+pub fn eval(
+    refraction_index: f32,
+    tint: Rgb<f32>,
+    ggx: Ggx,
+    ray_hit: &RayHit,
+    incoming: Vec3<f32>,
+    outgoing: Vec3<f32>,
+) -> Rgb<f32> {
+    if ggx.is_smooth() {
+        return Rgb::zero();
+    }
+
+    let eta = relative_eta(refraction_index, ray_hit);
+    let basis = Onb::new(ray_hit.normal);
+    let incoming = basis.to_local(incoming);
+
+    let value = eval_local(eta, ggx, incoming, basis.to_local(outgoing));
+
+    tinted(value, entry_tint(tint, ray_hit), incoming)
+}
+
+/// Tints only where paths go into the glass, so they're tinted once going through a closed
+/// object instead of twice
+fn entry_tint(tint: Rgb<f32>, ray_hit: &RayHit) -> Rgb<f32> {
+    match ray_hit.face {
+        Face::Front => tint,
+        Face::Back => Rgb::white(),
+    }
+}
+
+/// Only refracted light is tinted, `incoming` is in local space
+fn tinted(value: f32, tint: Rgb<f32>, incoming: Vec3<f32>) -> Rgb<f32> {
+    if incoming.z < 0. {
+        tint * value
+    } else {
+        Rgb::broadcast(value)
+    }
+}
+
+pub fn pdf(
+    refraction_index: f32,
+    ggx: Ggx,
+    ray_hit: &RayHit,
+    incoming: Vec3<f32>,
+    outgoing: Vec3<f32>,
+) -> f32 {
+    if ggx.is_smooth() {
+        return 0.;
+    }
+
+    let eta = relative_eta(refraction_index, ray_hit);
+    let basis = Onb::new(ray_hit.normal);
+
+    pdf_local(eta, ggx, basis.to_local(incoming), basis.to_local(outgoing))
+}
+
+/// The microfacet normal that reflects or refracts `outgoing` into `incoming`, facing up
+fn generalized_half_vector(
+    eta: f32,
+    incoming: Vec3<f32>,
+    outgoing: Vec3<f32>,
+) -> Option<Vec3<f32>> {
+    if outgoing.z <= 0. || incoming.z == 0. {
+        return None;
+    }
+
+    let eta = if incoming.z > 0. { 1. } else { eta };
+    let normal = incoming * eta + outgoing;
+
+    if normal.is_approx_zero() {
+        return None;
+    }
+
+    let normal = normal.normalized();
+    let normal = if normal.z < 0. { -normal } else { normal };
+
+    // Microfacets facing away from either direction don't contribute
+    if normal.dot(incoming) * incoming.z < 0. || normal.dot(outgoing) < 0. {
+        return None;
+    }
+
+    Some(normal)
+}
+
+/// Scalar since the glass is colorless. Radiance isn't scaled by the relative index of
+/// refraction, same as smooth glass
+fn eval_local(eta: f32, ggx: Ggx, incoming: Vec3<f32>, outgoing: Vec3<f32>) -> f32 {
+    let Some(normal) = generalized_half_vector(eta, incoming, outgoing) else {
+        return 0.;
+    };
+
+    let fresnel = fresnel_dielectric(outgoing.dot(normal), eta);
+    let distribution = ggx.distribution(normal);
+    let masking_shadowing = ggx.masking_shadowing(incoming, outgoing);
+
+    if incoming.z > 0. {
+        fresnel * distribution * masking_shadowing / (4. * outgoing.z)
+    } else {
+        let denominator = incoming.dot(normal) + outgoing.dot(normal) / eta;
+
+        (1. - fresnel)
+            * distribution
+            * masking_shadowing
+            * (incoming.dot(normal) * outgoing.dot(normal)).abs()
+            / (outgoing.z * denominator * denominator)
+    }
+}
+
+fn pdf_local(eta: f32, ggx: Ggx, incoming: Vec3<f32>, outgoing: Vec3<f32>) -> f32 {
+    let Some(normal) = generalized_half_vector(eta, incoming, outgoing) else {
+        return 0.;
+    };
+
+    let fresnel = fresnel_dielectric(outgoing.dot(normal), eta);
+    let normal_pdf = ggx.visible_normal_pdf(outgoing, normal);
+
+    if incoming.z > 0. {
+        fresnel * normal_pdf / (4. * outgoing.dot(normal))
+    } else {
+        let denominator = incoming.dot(normal) + outgoing.dot(normal) / eta;
+
+        (1. - fresnel) * normal_pdf * incoming.dot(normal).abs() / (denominator * denominator)
+    }
+}
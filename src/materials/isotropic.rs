@@ -0,0 +1,26 @@
+use crate::data::{BsdfSample, RayHit};
+use crate::extensions::RngExtension;
+use crate::texture::Texture;
+use rand::Rng;
+use std::f32::consts::PI;
+use std::option::Option;
+use vek::Rgb;
+
+pub fn sample(albedo: &Texture, ray_hit: &RayHit, rng: &mut impl Rng) -> Option<BsdfSample> {
+    Some(BsdfSample {
+        direction: rng.random_unit_vector(),
+        value: eval(albedo, ray_hit),
+        pdf: pdf(),
+        is_specular: false,
+    })
+}
+
+/// The phase function, there's no cosine term inside a medium
+pub fn eval(albedo: &Texture, ray_hit: &RayHit) -> Rgb<f32> {
+    albedo.color_at(ray_hit.uv, ray_hit.point) * pdf()
+}
+
+/// Uniform over the whole sphere of directions
+pub fn pdf() -> f32 {
+    1. / (4. * PI)
+}
@@ -1,27 +1,136 @@
-use crate::data::{Ray, RayHit, ScatterResult};
-use crate::extensions::RngExtension;
-use crate::texture::Texture;
+use crate::data::{BsdfSample, Onb, RayHit};
+use crate::materials::microfacet::Ggx;
 use rand::Rng;
 use std::option::Option;
+use vek::{Rgb, Vec2, Vec3};
 
-pub fn scatter(
-    albedo: &Texture,
-    fuzz: f32,
-    ray: Ray,
+/// Measured complex indices of refraction, sampled at roughly 650, 550 and 450 nm
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConductorPreset {
+    Gold,
+    Copper,
+    Aluminium,
+    Silver,
+}
+
+impl ConductorPreset {
+    /// The real and imaginary parts, `eta` and `k`
+    pub fn ior(self) -> (Rgb<f32>, Rgb<f32>) {
+        match self {
+            ConductorPreset::Gold => (Rgb::new(0.143, 0.374, 1.442), Rgb::new(3.983, 2.385, 1.603)),
+            ConductorPreset::Copper => {
+                (Rgb::new(0.200, 0.924, 1.102), Rgb::new(3.912, 2.452, 2.142))
+            }
+            ConductorPreset::Aluminium => {
+                (Rgb::new(1.657, 0.880, 0.521), Rgb::new(9.224, 6.269, 4.837))
+            }
+            ConductorPreset::Silver => {
+                (Rgb::new(0.155, 0.117, 0.138), Rgb::new(4.828, 3.122, 2.147))
+            }
+        }
+    }
+}
+
+/// Anisotropic highlights are stretched around the vertical axis
+fn tangent_space(ray_hit: &RayHit) -> Onb {
+    Onb::with_reference(ray_hit.normal, Vec3::unit_y())
+}
+
+/// `reflectance` is the fresnel term for the cosine between the outgoing direction and the
+/// microfacet normal
+pub fn sample(
+    reflectance: impl Fn(f32) -> Rgb<f32>,
+    ggx: Ggx,
+    outgoing: Vec3<f32>,
     ray_hit: &RayHit,
     rng: &mut impl Rng,
-) -> Option<ScatterResult> {
-    let reflected = ray.direction.normalized().reflected(ray_hit.normal);
+) -> Option<BsdfSample> {
+    if ggx.is_smooth() {
+        let direction = (-outgoing).reflected(ray_hit.normal);
+        let attenuation = reflectance(outgoing.dot(ray_hit.normal));
+
+        return Some(BsdfSample::specular(direction, attenuation));
+    }
 
-    let scattered = Ray::new(ray_hit.point, reflected + rng.random_unit_vector() * fuzz);
-    let attenuation = albedo.color_at(ray_hit.uv, ray_hit.point);
+    let basis = tangent_space(ray_hit);
+    let outgoing = basis.to_local(outgoing);
 
-    if scattered.direction.dot(ray_hit.normal) > 0. {
-        Some(ScatterResult {
-            scattered,
-            attenuation,
-        })
-    } else {
-        None
+    if outgoing.z <= 0. {
+        return None;
     }
+
+    let normal = ggx.sample_visible_normal(outgoing, Vec2::new(rng.gen(), rng.gen()));
+    let incoming = (-outgoing).reflected(normal);
+
+    // Reflected into the surface, that energy is lost
+    if incoming.z <= 0. {
+        return None;
+    }
+
+    Some(BsdfSample {
+        direction: basis.to_world(incoming),
+        value: eval_local(&reflectance, ggx, incoming, outgoing),
+        pdf: pdf_local(ggx, incoming, outgoing),
+        is_specular: false,
+    })
+}
+
+pub fn eval(
+    reflectance: impl Fn(f32) -> Rgb<f32>,
+    ggx: Ggx,
+    ray_hit: &RayHit,
+    incoming: Vec3<f32>,
+    outgoing: Vec3<f32>,
+) -> Rgb<f32> {
+    if ggx.is_smooth() {
+        return Rgb::zero();
+    }
+
+    let basis = tangent_space(ray_hit);
+
+    eval_local(
+        &reflectance,
+        ggx,
+        basis.to_local(incoming),
+        basis.to_local(outgoing),
+    )
+}
+
+pub fn pdf(ggx: Ggx, ray_hit: &RayHit, incoming: Vec3<f32>, outgoing: Vec3<f32>) -> f32 {
+    if ggx.is_smooth() {
+        return 0.;
+    }
+
+    let basis = tangent_space(ray_hit);
+
+    pdf_local(ggx, basis.to_local(incoming), basis.to_local(outgoing))
+}
+
+fn eval_local(
+    reflectance: impl Fn(f32) -> Rgb<f32>,
+    ggx: Ggx,
+    incoming: Vec3<f32>,
+    outgoing: Vec3<f32>,
+) -> Rgb<f32> {
+    if incoming.z <= 0. || outgoing.z <= 0. {
+        return Rgb::zero();
+    }
+
+    let normal = (incoming + outgoing).normalized();
+
+    let distribution = ggx.distribution(normal);
+    let masking_shadowing = ggx.masking_shadowing(incoming, outgoing);
+
+    // The cosine term cancels out the incoming cosine in the denominator
+    reflectance(outgoing.dot(normal)) * (distribution * masking_shadowing / (4. * outgoing.z))
+}
+
+fn pdf_local(ggx: Ggx, incoming: Vec3<f32>, outgoing: Vec3<f32>) -> f32 {
+    if incoming.z <= 0. || outgoing.z <= 0. {
+        return 0.;
+    }
+
+    let normal = (incoming + outgoing).normalized();
+
+    ggx.visible_normal_pdf(outgoing, normal) / (4. * outgoing.dot(normal))
 }
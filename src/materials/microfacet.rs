@@ -0,0 +1,157 @@
+use std::f32::consts::PI;
+use vek::{Rgb, Vec2, Vec3};
+
+/// Below this alpha surfaces are treated as perfectly smooth
+const SMOOTH_ALPHA: f32 = 1e-3;
+
+/// GGX / Trowbridge-Reitz distribution of microfacet normals. Directions are in a local
+/// space where the surface normal is z and the tangent is x
+#[derive(Debug, Clone, Copy)]
+pub struct Ggx {
+    pub alpha_x: f32,
+    pub alpha_y: f32,
+}
+
+impl Ggx {
+    /// `roughness` is perceptual, squared to get alpha. `anisotropy` from 0 to 1 stretches
+    /// the highlight along the tangent
+    pub fn new(roughness: f32, anisotropy: f32) -> Self {
+        let alpha = roughness * roughness;
+        let aspect = f32::sqrt(1. - 0.9 * anisotropy.clamp(0., 1.));
+
+        Self {
+            alpha_x: alpha / aspect,
+            alpha_y: alpha * aspect,
+        }
+    }
+
+    pub fn is_smooth(self) -> bool {
+        self.alpha_x.max(self.alpha_y) < SMOOTH_ALPHA
+    }
+
+    /// Density of microfacets facing `normal`
+    pub fn distribution(self, normal: Vec3<f32>) -> f32 {
+        let x = normal.x / self.alpha_x;
+        let y = normal.y / self.alpha_y;
+        let t = x * x + y * y + normal.z * normal.z;
+
+        1. / (PI * self.alpha_x * self.alpha_y * t * t)
+    }
+
+    /// Smith's auxiliary function
+    fn lambda(self, direction: Vec3<f32>) -> f32 {
+        let x = self.alpha_x * direction.x;
+        let y = self.alpha_y * direction.y;
+        let z_squared = direction.z * direction.z;
+
+        if z_squared == 0. {
+            return f32::INFINITY;
+        }
+
+        (f32::sqrt(1. + (x * x + y * y) / z_squared) - 1.) / 2.
+    }
+
+    /// Fraction of microfacets visible from `direction`
+    pub fn masking(self, direction: Vec3<f32>) -> f32 {
+        1. / (1. + self.lambda(direction))
+    }
+
+    /// Height correlated masking and shadowing
+    pub fn masking_shadowing(self, incoming: Vec3<f32>, outgoing: Vec3<f32>) -> f32 {
+        1. / (1. + self.lambda(incoming) + self.lambda(outgoing))
+    }
+
+    /// Density of `sample_visible_normal` picking `normal` when seen from `direction`
+    pub fn visible_normal_pdf(self, direction: Vec3<f32>, normal: Vec3<f32>) -> f32 {
+        let cosine = direction.dot(normal).max(0.);
+
+        self.masking(direction) * cosine * self.distribution(normal) / direction.z.abs()
+    }
+
+    /// Samples a microfacet normal visible from `direction`, which must be above the surface
+    pub fn sample_visible_normal(self, direction: Vec3<f32>, random: Vec2<f32>) -> Vec3<f32> {
+        // Stretch to a hemisphere configuration
+        let hemisphere = Vec3::new(
+            self.alpha_x * direction.x,
+            self.alpha_y * direction.y,
+            direction.z,
+        )
+        .normalized();
+
+        let length_squared = hemisphere.x * hemisphere.x + hemisphere.y * hemisphere.y;
+        let t1 = if length_squared > 0. {
+            Vec3::new(-hemisphere.y, hemisphere.x, 0.) / length_squared.sqrt()
+        } else {
+            Vec3::unit_x()
+        };
+        let t2 = hemisphere.cross(t1);
+
+        // Point on the projected area of the visible half disk
+        let radius = random.x.sqrt();
+        let phi = 2. * PI * random.y;
+        let p1 = radius * phi.cos();
+        let p2 = radius * phi.sin();
+        let s = 0.5 * (1. + hemisphere.z);
+        let p2 = (1. - s) * f32::sqrt(1. - p1 * p1) + s * p2;
+
+        let normal =
+            t1 * p1 + t2 * p2 + hemisphere * f32::sqrt(f32::max(0., 1. - p1 * p1 - p2 * p2));
+
+        // Back to the ellipsoid configuration
+        Vec3::new(
+            self.alpha_x * normal.x,
+            self.alpha_y * normal.y,
+            f32::max(1e-6, normal.z),
+        )
+        .normalized()
+    }
+}
+
+pub fn fresnel_schlick(f0: Rgb<f32>, cosine: f32) -> Rgb<f32> {
+    f0 + (Rgb::one() - f0) * f32::powi(1. - cosine.clamp(0., 1.), 5)
+}
+
+/// Reflectance of a dielectric, `eta` is the index of refraction on the other side of the
+/// surface relative to this one
+pub fn fresnel_dielectric(cosine: f32, eta: f32) -> f32 {
+    let cos_i = cosine.clamp(0., 1.);
+    let sin2_i = 1. - cos_i * cos_i;
+    let sin2_t = sin2_i / (eta * eta);
+
+    // Total internal reflection
+    if sin2_t >= 1. {
+        return 1.;
+    }
+
+    let cos_t = f32::sqrt(1. - sin2_t);
+
+    let parallel = (eta * cos_i - cos_t) / (eta * cos_i + cos_t);
+    let perpendicular = (cos_i - eta * cos_t) / (cos_i + eta * cos_t);
+
+    (parallel * parallel + perpendicular * perpendicular) / 2.
+}
+
+/// Reflectance of a conductor with the complex index of refraction `eta + i k`
+pub fn fresnel_conductor(cosine: f32, eta: Rgb<f32>, k: Rgb<f32>) -> Rgb<f32> {
+    let cos2 = cosine.clamp(0., 1.).powi(2);
+    let sin2 = 1. - cos2;
+
+    eta.map2(k, |eta, k| {
+        let eta2 = eta * eta;
+        let k2 = k * k;
+
+        let t0 = eta2 - k2 - sin2;
+        let a2_plus_b2 = f32::sqrt(t0 * t0 + 4. * eta2 * k2);
+        let a = f32::sqrt(0.5 * (a2_plus_b2 + t0));
+
+        let t1 = a2_plus_b2 + cos2;
+        let t2 = 2. * cos2.sqrt() * a;
+        let perpendicular = (t1 - t2) / (t1 + t2);
+
+        let t3 = cos2 * a2_plus_b2 + sin2 * sin2;
+        let t4 = t2 * sin2;
+        let parallel = perpendicular * (t3 - t4) / (t3 + t4);
+
+        (parallel + perpendicular) / 2.
+    })
+}
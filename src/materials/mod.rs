@@ -1,42 +1,273 @@
-use crate::data::{Ray, RayHit, ScatterResult};
+use crate::data::{BsdfSample, RayHit};
+use crate::lights::Cone;
 use crate::texture::Texture;
+use microfacet::{fresnel_conductor, fresnel_schlick, Ggx};
 use rand::Rng;
 use std::fmt::Debug;
-use vek::{Rgb, Vec2, Vec3};
+use std::sync::Arc;
+use vek::{Rgb, Vec3};
 
 mod diffuse;
 mod diffuse_light;
 mod glass;
+mod isotropic;
 mod metal;
+pub mod microfacet;
+mod principled;
+
+pub use glass::{sample_wavelength, transmittance, Dispersion};
+pub use metal::ConductorPreset;
+pub use principled::Principled;
 
 #[derive(Debug, Clone)]
 pub enum Material {
-    Diffuse { albedo: Texture },
-    Metal { albedo: Texture, fuzz: f32 },
-    Glass { refraction_index: f32 },
-    DiffuseLight { strength: Texture },
+    Diffuse {
+        albedo: Texture,
+    },
+    /// Reflectance at normal incidence from `albedo`, with Schlick's fresnel
+    Metal {
+        albedo: Texture,
+        roughness: f32,
+        anisotropy: f32,
+    },
+
+    /// Fresnel from the complex index of refraction `eta + i k`
+    Conductor {
+        eta: Rgb<f32>,
+        k: Rgb<f32>,
+        roughness: f32,
+        anisotropy: f32,
+    },
+
+    /// Smooth when `roughness` is zero. `tint` colors refracted light and `absorption` is
+    /// the Beer-Lambert coefficient per unit of distance travelled inside
+    Glass {
+        refraction_index: f32,
+        roughness: f32,
+        tint: Rgb<f32>,
+        absorption: Rgb<f32>,
+        dispersion: Option<Dispersion>,
+    },
+
+    /// Emitted radiance is scaled by the cosine to the normal raised to `cosine_power`, so
+    /// zero gives a uniform emitter, and fades out at the edge of `cone` around the normal
+    DiffuseLight {
+        strength: Texture,
+        two_sided: bool,
+        cosine_power: f32,
+        cone: Option<Cone>,
+    },
+
+    /// Shared since it holds many textures and materials are cloned for every hit
+    Principled(Arc<Principled>),
+
+    /// Scatters in a uniformly random direction, used inside participating media
+    Isotropic {
+        albedo: Texture,
+    },
 }
 
+/// Directions are unit length and point away from the surface. `incoming` is towards where
+/// light comes from and `outgoing` towards the viewer. `wavelength` is set, in nanometers,
+/// once a path only carries one wavelength after going through dispersive glass
 impl Material {
-    pub fn scatter(&self, ray: Ray, ray_hit: &RayHit, rng: &mut impl Rng) -> Option<ScatterResult> {
+    pub fn sample(
+        &self,
+        outgoing: Vec3<f32>,
+        ray_hit: &RayHit,
+        wavelength: Option<f32>,
+        rng: &mut impl Rng,
+    ) -> Option<BsdfSample> {
         match self {
-            Material::Diffuse { albedo } => diffuse::scatter(albedo, ray_hit, rng),
-            Material::Metal { albedo, fuzz } => metal::scatter(albedo, *fuzz, ray, ray_hit, rng),
-            Material::Glass { refraction_index } => {
-                glass::scatter(*refraction_index, ray, ray_hit, rng)
+            Material::Diffuse { albedo } => diffuse::sample(albedo, ray_hit, rng),
+            Material::Metal {
+                albedo,
+                roughness,
+                anisotropy,
+            } => {
+                let f0 = albedo.color_at(ray_hit.uv, ray_hit.point);
+                let reflectance = |cosine| fresnel_schlick(f0, cosine);
+
+                metal::sample(
+                    reflectance,
+                    Ggx::new(*roughness, *anisotropy),
+                    outgoing,
+                    ray_hit,
+                    rng,
+                )
+            }
+            &Material::Conductor {
+                eta,
+                k,
+                roughness,
+                anisotropy,
+            } => {
+                let reflectance = |cosine| fresnel_conductor(cosine, eta, k);
+
+                metal::sample(
+                    reflectance,
+                    Ggx::new(roughness, anisotropy),
+                    outgoing,
+                    ray_hit,
+                    rng,
+                )
             }
+            &Material::Glass {
+                refraction_index,
+                roughness,
+                tint,
+                dispersion,
+                ..
+            } => glass::sample(
+                glass::refraction_index_at(refraction_index, dispersion, wavelength),
+                tint,
+                Ggx::new(roughness, 0.),
+                outgoing,
+                ray_hit,
+                rng,
+            ),
             Material::DiffuseLight { .. } => None,
+            Material::Principled(principled) => {
+                principled::sample(principled, outgoing, ray_hit, rng)
+            }
+            Material::Isotropic { albedo } => isotropic::sample(albedo, ray_hit, rng),
         }
     }
 
-    pub fn emit(&self, uv: Vec2<f32>, point: Vec3<f32>) -> Rgb<f32> {
+    /// The bsdf times the cosine term, zero for specular materials
+    pub fn eval(
+        &self,
+        ray_hit: &RayHit,
+        incoming: Vec3<f32>,
+        outgoing: Vec3<f32>,
+        wavelength: Option<f32>,
+    ) -> Rgb<f32> {
+        match self {
+            Material::Diffuse { albedo } => diffuse::eval(albedo, ray_hit, incoming),
+            Material::Metal {
+                albedo,
+                roughness,
+                anisotropy,
+            } => {
+                let f0 = albedo.color_at(ray_hit.uv, ray_hit.point);
+                let reflectance = |cosine| fresnel_schlick(f0, cosine);
+                let ggx = Ggx::new(*roughness, *anisotropy);
+
+                metal::eval(reflectance, ggx, ray_hit, incoming, outgoing)
+            }
+            &Material::Conductor {
+                eta,
+                k,
+                roughness,
+                anisotropy,
+            } => {
+                let reflectance = |cosine| fresnel_conductor(cosine, eta, k);
+                let ggx = Ggx::new(roughness, anisotropy);
+
+                metal::eval(reflectance, ggx, ray_hit, incoming, outgoing)
+            }
+            &Material::Glass {
+                refraction_index,
+                roughness,
+                tint,
+                dispersion,
+                ..
+            } => glass::eval(
+                glass::refraction_index_at(refraction_index, dispersion, wavelength),
+                tint,
+                Ggx::new(roughness, 0.),
+                ray_hit,
+                incoming,
+                outgoing,
+            ),
+            Material::DiffuseLight { .. } => Rgb::zero(),
+            Material::Principled(principled) => {
+                principled::eval(principled, ray_hit, incoming, outgoing)
+            }
+            Material::Isotropic { albedo } => isotropic::eval(albedo, ray_hit),
+        }
+    }
+
+    /// Probability density of `sample` picking `incoming`, zero for specular materials
+    pub fn pdf(
+        &self,
+        ray_hit: &RayHit,
+        incoming: Vec3<f32>,
+        outgoing: Vec3<f32>,
+        wavelength: Option<f32>,
+    ) -> f32 {
+        match *self {
+            Material::Diffuse { .. } => diffuse::pdf(ray_hit, incoming),
+            Material::Metal {
+                roughness,
+                anisotropy,
+                ..
+            }
+            | Material::Conductor {
+                roughness,
+                anisotropy,
+                ..
+            } => metal::pdf(Ggx::new(roughness, anisotropy), ray_hit, incoming, outgoing),
+            Material::Glass {
+                refraction_index,
+                roughness,
+                dispersion,
+                ..
+            } => glass::pdf(
+                glass::refraction_index_at(refraction_index, dispersion, wavelength),
+                Ggx::new(roughness, 0.),
+                ray_hit,
+                incoming,
+                outgoing,
+            ),
+            Material::DiffuseLight { .. } => 0.,
+            Material::Principled(ref principled) => {
+                principled::pdf(principled, ray_hit, incoming, outgoing)
+            }
+            Material::Isotropic { .. } => isotropic::pdf(),
+        }
+    }
+
+    pub fn is_dispersive(&self) -> bool {
+        matches!(
+            self,
+            Material::Glass {
+                dispersion: Some(_),
+                ..
+            }
+        )
+    }
+
+    /// Absorption coefficients of the medium inside the material, for materials that paths
+    /// can go into
+    pub fn absorption(&self) -> Option<Rgb<f32>> {
+        match self {
+            &Material::Glass { absorption, .. } => Some(absorption),
+            _ => None,
+        }
+    }
+
+    pub fn is_emissive(&self) -> bool {
+        matches!(self, Material::DiffuseLight { .. })
+    }
+
+    /// Light leaving the hit towards `outgoing`
+    pub fn emit(&self, ray_hit: &RayHit, outgoing: Vec3<f32>) -> Rgb<f32> {
         let none = Rgb::zero();
 
         match self {
             Material::Diffuse { .. } => none,
             Material::Metal { .. } => none,
+            Material::Conductor { .. } => none,
             Material::Glass { .. } => none,
-            Material::DiffuseLight { strength } => diffuse_light::emit(strength, uv, point),
+            &Material::DiffuseLight {
+                ref strength,
+                two_sided,
+                cosine_power,
+                cone,
+            } => diffuse_light::emit(strength, two_sided, cosine_power, cone, ray_hit, outgoing),
+            Material::Principled(_) => none,
+            Material::Isotropic { .. } => none,
         }
     }
 }
@@ -0,0 +1,263 @@
+use crate::data::{BsdfSample, Onb, RayHit};
+use crate::extensions::RngExtension;
+use crate::materials::microfacet::{fresnel_schlick, Ggx};
+use crate::materials::{diffuse, glass, metal};
+use crate::texture::Texture;
+use rand::Rng;
+use std::f32::consts::PI;
+use vek::{Rgb, Vec3};
+
+/// Below this the microfacet lobes would become delta lobes, which can't be mixed
+const MIN_ROUGHNESS: f32 = 0.05;
+
+/// Reflectance at normal incidence of the clearcoat, a polyurethane like layer
+const CLEARCOAT_F0: f32 = 0.04;
+
+/// Disney style material mixing diffuse, sheen, specular, transmission and clearcoat lobes.
+/// Scalar parameters are read from the average of their texture's channels
+#[derive(Debug, Clone)]
+pub struct Principled {
+    pub base_color: Texture,
+    pub metallic: Texture,
+    pub roughness: Texture,
+
+    /// Reflectance of the dielectric part, 0.5 is 4% at normal incidence
+    pub specular: Texture,
+
+    /// Soft retroreflection at grazing angles, for cloth
+    pub sheen: Texture,
+
+    pub clearcoat: Texture,
+    pub clearcoat_roughness: Texture,
+
+    /// How much of the dielectric part is glass instead of diffuse
+    pub transmission: Texture,
+
+    pub refraction_index: f32,
+    pub anisotropy: f32,
+}
+
+impl Principled {
+    /// Rough dielectric with Blender's defaults
+    pub fn new(base_color: Texture) -> Self {
+        let value = |value| Texture::solid(Rgb::broadcast(value));
+
+        Self {
+            base_color,
+            metallic: value(0.),
+            roughness: value(0.5),
+            specular: value(0.5),
+            sheen: value(0.),
+            clearcoat: value(0.),
+            clearcoat_roughness: value(0.03),
+            transmission: value(0.),
+            refraction_index: 1.5,
+            anisotropy: 0.,
+        }
+    }
+
+    fn parameters(&self, ray_hit: &RayHit) -> Parameters {
+        let color = |texture: &Texture| texture.color_at(ray_hit.uv, ray_hit.point);
+        let scalar = |texture: &Texture| {
+            let color = color(texture);
+            ((color.r + color.g + color.b) / 3.).clamp(0., 1.)
+        };
+
+        let metallic = scalar(&self.metallic);
+        let transmission = scalar(&self.transmission);
+        let base_color = color(&self.base_color);
+        let dielectric_f0 = Rgb::broadcast(0.08 * scalar(&self.specular));
+
+        Parameters {
+            base_color,
+            sheen: scalar(&self.sheen),
+            specular_f0: Rgb::lerp(dielectric_f0, base_color, metallic),
+
+            specular: Ggx::new(scalar(&self.roughness).max(MIN_ROUGHNESS), self.anisotropy),
+            clearcoat: Ggx::new(scalar(&self.clearcoat_roughness).max(MIN_ROUGHNESS), 0.),
+            refraction_index: self.refraction_index,
+
+            weights: [
+                1. - (1. - metallic) * transmission,
+                (1. - metallic) * (1. - transmission),
+                (1. - metallic) * transmission,
+                0.25 * scalar(&self.clearcoat),
+            ],
+        }
+    }
+}
+
+/// The material's textures looked up at a hit
+struct Parameters {
+    base_color: Rgb<f32>,
+    sheen: f32,
+    specular_f0: Rgb<f32>,
+
+    specular: Ggx,
+    clearcoat: Ggx,
+    refraction_index: f32,
+
+    /// Of the specular, diffuse, transmission and clearcoat lobes, also used as the odds of
+    /// sampling each of them
+    weights: [f32; 4],
+}
+
+impl Parameters {
+    fn eval(&self, ray_hit: &RayHit, incoming: Vec3<f32>, outgoing: Vec3<f32>) -> Rgb<f32> {
+        let [specular_weight, diffuse_weight, transmission_weight, clearcoat_weight] = self.weights;
+
+        let mut value = Rgb::zero();
+
+        if specular_weight > 0. {
+            let reflectance = |cosine| fresnel_schlick(self.specular_f0, cosine);
+            value += metal::eval(reflectance, self.specular, ray_hit, incoming, outgoing)
+                * specular_weight;
+        }
+
+        let cosine = ray_hit.normal.dot(incoming);
+
+        if diffuse_weight > 0. && cosine > 0. {
+            let half_vector = (incoming + outgoing).normalized();
+            let sheen = self.sheen * f32::powi(1. - incoming.dot(half_vector).max(0.), 5);
+
+            // Light the specular lobe reflects doesn't reach the diffuse layer below it
+            let fresnel = fresnel_schlick(self.specular_f0, ray_hit.normal.dot(outgoing));
+            let diffuse = self.base_color * (Rgb::one() - fresnel) / PI;
+
+            value += (diffuse + Rgb::broadcast(sheen)) * (cosine * diffuse_weight);
+        }
+
+        if transmission_weight > 0. {
+            let glass = glass::eval(
+                self.refraction_index,
+                self.base_color,
+                self.specular,
+                ray_hit,
+                incoming,
+                outgoing,
+            );
+
+            value += glass * transmission_weight;
+        }
+
+        if clearcoat_weight > 0. {
+            let reflectance = |cosine| fresnel_schlick(Rgb::broadcast(CLEARCOAT_F0), cosine);
+
+            // The layers below only see the light the clearcoat lets through
+            let coat_fresnel = reflectance(ray_hit.normal.dot(outgoing));
+            value *= Rgb::one() - coat_fresnel * clearcoat_weight;
+
+            value += metal::eval(reflectance, self.clearcoat, ray_hit, incoming, outgoing)
+                * clearcoat_weight;
+        }
+
+        value
+    }
+
+    fn pdf(&self, ray_hit: &RayHit, incoming: Vec3<f32>, outgoing: Vec3<f32>) -> f32 {
+        let total_weight = self.weights.iter().sum::<f32>();
+
+        let pdfs = [
+            metal::pdf(self.specular, ray_hit, incoming, outgoing),
+            diffuse::pdf(ray_hit, incoming),
+            glass::pdf(
+                self.refraction_index,
+                self.specular,
+                ray_hit,
+                incoming,
+                outgoing,
+            ),
+            metal::pdf(self.clearcoat, ray_hit, incoming, outgoing),
+        ];
+
+        self.weights
+            .iter()
+            .zip(pdfs)
+            .map(|(weight, pdf)| weight * pdf)
+            .sum::<f32>()
+            / total_weight
+    }
+
+    /// Index of a lobe picked with odds proportional to its weight
+    fn pick_lobe(&self, rng: &mut impl Rng) -> usize {
+        let mut choice = rng.gen::<f32>() * self.weights.iter().sum::<f32>();
+
+        for (index, &weight) in self.weights.iter().enumerate() {
+            if choice < weight {
+                return index;
+            }
+
+            choice -= weight;
+        }
+
+        // Rounding can leave a tiny remainder, fall back to the last lobe in use
+        self.weights
+            .iter()
+            .rposition(|&weight| weight > 0.)
+            .unwrap()
+    }
+}
+
+pub fn sample(
+    principled: &Principled,
+    outgoing: Vec3<f32>,
+    ray_hit: &RayHit,
+    rng: &mut impl Rng,
+) -> Option<BsdfSample> {
+    let parameters = principled.parameters(ray_hit);
+
+    // Any reflectance works, only the direction is used
+    let reflectance = |_| Rgb::one();
+
+    let direction = match parameters.pick_lobe(rng) {
+        0 => metal::sample(reflectance, parameters.specular, outgoing, ray_hit, rng)?.direction,
+        1 => Onb::new(ray_hit.normal).to_world(rng.random_cosine_direction()),
+        2 => {
+            glass::sample(
+                parameters.refraction_index,
+                parameters.base_color,
+                parameters.specular,
+                outgoing,
+                ray_hit,
+                rng,
+            )?
+            .direction
+        }
+        _ => metal::sample(reflectance, parameters.clearcoat, outgoing, ray_hit, rng)?.direction,
+    };
+
+    let pdf = parameters.pdf(ray_hit, direction, outgoing);
+
+    if pdf <= 0. {
+        return None;
+    }
+
+    Some(BsdfSample {
+        direction,
+        value: parameters.eval(ray_hit, direction, outgoing),
+        pdf,
+        is_specular: false,
+    })
+}
+
+pub fn eval(
+    principled: &Principled,
+    ray_hit: &RayHit,
+    incoming: Vec3<f32>,
+    outgoing: Vec3<f32>,
+) -> Rgb<f32> {
+    principled
+        .parameters(ray_hit)
+        .eval(ray_hit, incoming, outgoing)
+}
+
+pub fn pdf(
+    principled: &Principled,
+    ray_hit: &RayHit,
+    incoming: Vec3<f32>,
+    outgoing: Vec3<f32>,
+) -> f32 {
+    principled
+        .parameters(ray_hit)
+        .pdf(ray_hit, incoming, outgoing)
+}
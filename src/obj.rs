@@ -0,0 +1,719 @@
+use crate::image_io::{open_image, ColorSpace};
+use crate::materials::{Material, Principled};
+use crate::shapes::mesh::Mesh;
+use crate::texture::Texture;
+use image::{ImageError, Rgb32FImage};
+use std::collections::HashMap;
+use std::fmt::{self, Display};
+use std::path::{Path, PathBuf};
+use std::str::{FromStr, SplitWhitespace};
+use std::sync::Arc;
+use std::{fs, io};
+use vek::{Rgb, Vec2, Vec3};
+
+#[derive(Debug)]
+pub enum ObjError {
+    Io(PathBuf, io::Error),
+    Image(PathBuf, ImageError),
+
+    /// A malformed line, with its 1-based line number
+    Syntax {
+        path: PathBuf,
+        line: usize,
+        message: String,
+    },
+
+    UnknownMaterial(PathBuf, String),
+    Empty(PathBuf),
+}
+
+impl Display for ObjError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ObjError::Io(path, error) => write!(f, "failed to read {}: {error}", path.display()),
+            ObjError::Image(path, error) => {
+                write!(f, "failed to load image {}: {error}", path.display())
+            }
+            ObjError::Syntax {
+                path,
+                line,
+                message,
+            } => write!(f, "{}:{line}: {message}", path.display()),
+            ObjError::UnknownMaterial(path, name) => {
+                write!(f, "{}: unknown material \"{name}\"", path.display())
+            }
+            ObjError::Empty(path) => write!(f, "{} contains no faces", path.display()),
+        }
+    }
+}
+
+impl std::error::Error for ObjError {}
+
+/// Used for faces without a `usemtl`
+fn default_material() -> Material {
+    Material::Diffuse {
+        albedo: Texture::solid(Rgb::broadcast(0.8)),
+    }
+}
+
+/// A vertex of a face, as indices into the position, texture coordinate and normal lists
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct VertexIndices {
+    position: usize,
+    uv: Option<usize>,
+    normal: Option<usize>,
+}
+
+/// Faces sharing the same group and material, becomes one mesh
+#[derive(Debug, Default)]
+struct FaceGroup {
+    triangles: Vec<[VertexIndices; 3]>,
+}
+
+/// Loads a Wavefront OBJ file, with the materials from the MTL files it references
+///
+/// Every group and material combination becomes its own [`Mesh`]. If `material_override` is
+/// given it's used for every face instead, and MTL files are not read.
+pub fn load_obj(
+    path: impl AsRef<Path>,
+    material_override: Option<Material>,
+) -> Result<Vec<Mesh>, ObjError> {
+    let path = path.as_ref();
+    let source = fs::read_to_string(path).map_err(|error| ObjError::Io(path.into(), error))?;
+    let directory = path.parent().unwrap_or(Path::new("."));
+
+    let mut positions = Vec::new();
+    let mut uvs = Vec::new();
+    let mut normals = Vec::new();
+
+    let mut materials = HashMap::new();
+    let mut images = HashMap::new();
+
+    let mut group = String::new();
+    let mut material = None::<String>;
+
+    // Keeps insertion order so the output is deterministic
+    let mut face_groups = Vec::<((String, Option<String>), FaceGroup)>::new();
+
+    for (line_index, line) in source.lines().enumerate() {
+        let mut parser = LineParser {
+            path,
+            line: line_index + 1,
+            tokens: strip_comment(line).split_whitespace(),
+        };
+
+        let Some(keyword) = parser.tokens.next() else {
+            continue;
+        };
+
+        match keyword {
+            "v" => positions.push(parser.vec3()?),
+            "vn" => normals.push(parser.vec3()?),
+            "vt" => {
+                let u = parser.number()?;
+                let v = parser.optional_number()?.unwrap_or(0.);
+
+                uvs.push(Vec2::new(u, v));
+            }
+
+            "f" => {
+                let vertices = parser
+                    .tokens
+                    .clone()
+                    .map(|token| {
+                        parser.face_vertex(token, positions.len(), uvs.len(), normals.len())
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+
+                if vertices.len() < 3 {
+                    return Err(parser.error(format!(
+                        "face has {} vertices, expected at least 3",
+                        vertices.len()
+                    )));
+                }
+
+                let key = (group.clone(), material.clone());
+                let face_group = match face_groups.iter_mut().find(|(other, _)| *other == key) {
+                    Some((_, face_group)) => face_group,
+                    None => {
+                        face_groups.push((key, FaceGroup::default()));
+                        &mut face_groups.last_mut().unwrap().1
+                    }
+                };
+
+                // Triangulate as a fan around the first vertex
+                for window in vertices[1..].windows(2) {
+                    face_group
+                        .triangles
+                        .push([vertices[0], window[0], window[1]]);
+                }
+            }
+
+            "g" | "o" => group = parser.tokens.collect::<Vec<_>>().join(" "),
+            "usemtl" => material = Some(parser.name()?),
+
+            "mtllib" if material_override.is_none() => {
+                for file in parser.tokens {
+                    load_mtl(&directory.join(file), &mut materials, &mut images)?;
+                }
+            }
+
+            // Smoothing groups, lines, points and other statements are ignored
+            _ => {}
+        }
+    }
+
+    if face_groups.is_empty() {
+        return Err(ObjError::Empty(path.into()));
+    }
+
+    face_groups
+        .into_iter()
+        .map(|((_, material_name), face_group)| {
+            let material = match (&material_override, material_name) {
+                (Some(material), _) => material.clone(),
+                (None, Some(name)) => materials
+                    .get(&name)
+                    .cloned()
+                    .ok_or_else(|| ObjError::UnknownMaterial(path.into(), name))?,
+                (None, None) => default_material(),
+            };
+
+            build_mesh(&face_group, &positions, &uvs, &normals, material)
+                .ok_or_else(|| ObjError::Empty(path.into()))
+        })
+        .collect()
+}
+
+/// Turns the separately indexed OBJ attributes into one shared index per vertex, None if
+/// there are no faces
+fn build_mesh(
+    face_group: &FaceGroup,
+    positions: &[Vec3<f32>],
+    uvs: &[Vec2<f32>],
+    normals: &[Vec3<f32>],
+    material: Material,
+) -> Option<Mesh> {
+    let mut vertex_lookup = HashMap::new();
+    let mut vertices = Vec::new();
+
+    let indices = face_group
+        .triangles
+        .iter()
+        .map(|triangle| {
+            triangle.map(|vertex| {
+                *vertex_lookup.entry(vertex).or_insert_with(|| {
+                    vertices.push(vertex);
+                    (vertices.len() - 1) as u32
+                })
+            })
+        })
+        .collect::<Vec<_>>();
+
+    let mesh_positions = vertices
+        .iter()
+        .map(|vertex| positions[vertex.position])
+        .collect();
+
+    // Attributes are only used if every vertex has them
+    let mesh_uvs = vertices
+        .iter()
+        .map(|vertex| vertex.uv.map(|uv| uvs[uv]))
+        .collect::<Option<Vec<_>>>();
+
+    let mesh_normals = vertices
+        .iter()
+        .map(|vertex| vertex.normal.map(|normal| normals[normal].normalized()))
+        .collect::<Option<Vec<_>>>();
+
+    Mesh::new(mesh_positions, &indices, mesh_normals, mesh_uvs, material)
+}
+
+#[derive(Debug, Default)]
+struct MtlDescription {
+    diffuse: Option<Rgb<f32>>,
+    diffuse_map: Option<PathBuf>,
+    specular: Option<Rgb<f32>>,
+    specular_exponent: Option<f32>,
+    emission: Option<Rgb<f32>>,
+    refraction_index: Option<f32>,
+    dissolve: Option<f32>,
+    illumination: Option<u32>,
+
+    // From the PBR extension
+    roughness: Option<f32>,
+    metallic: Option<f32>,
+    sheen: Option<f32>,
+    clearcoat: Option<f32>,
+    clearcoat_roughness: Option<f32>,
+}
+
+fn load_mtl(
+    path: &Path,
+    materials: &mut HashMap<String, Material>,
+    images: &mut HashMap<PathBuf, Arc<Rgb32FImage>>,
+) -> Result<(), ObjError> {
+    let source = fs::read_to_string(path).map_err(|error| ObjError::Io(path.into(), error))?;
+    let directory = path.parent().unwrap_or(Path::new("."));
+
+    let mut current = None::<(String, MtlDescription)>;
+    let mut descriptions = Vec::new();
+
+    for (line_index, line) in source.lines().enumerate() {
+        let mut parser = LineParser {
+            path,
+            line: line_index + 1,
+            tokens: strip_comment(line).split_whitespace(),
+        };
+
+        let Some(keyword) = parser.tokens.next() else {
+            continue;
+        };
+
+        if keyword == "newmtl" {
+            descriptions.extend(current.take());
+            current = Some((parser.name()?, MtlDescription::default()));
+            continue;
+        }
+
+        let Some((_, description)) = &mut current else {
+            return Err(parser.error(format!("\"{keyword}\" before any newmtl")));
+        };
+
+        match keyword {
+            "Kd" => description.diffuse = Some(parser.color()?),
+            "Ks" => description.specular = Some(parser.color()?),
+            "Ke" => description.emission = Some(parser.color()?),
+            "Ns" => description.specular_exponent = Some(parser.number()?),
+            "Ni" => description.refraction_index = Some(parser.number()?),
+            "d" => description.dissolve = Some(parser.number()?),
+            "Tr" => description.dissolve = Some(1. - parser.number::<f32>()?),
+            "illum" => description.illumination = Some(parser.number()?),
+            "Pr" => description.roughness = Some(parser.number()?),
+            "Pm" => description.metallic = Some(parser.number()?),
+            "Ps" => description.sheen = Some(parser.number()?),
+            "Pc" => description.clearcoat = Some(parser.number()?),
+            "Pcr" => description.clearcoat_roughness = Some(parser.number()?),
+
+            // Options like `-s 1 1 1` come before the file name, which is always last
+            "map_Kd" => match parser.tokens.clone().last() {
+                Some(file) => description.diffuse_map = Some(directory.join(file)),
+                None => return Err(parser.error("map_Kd without a file name".into())),
+            },
+
+            _ => {}
+        }
+    }
+
+    descriptions.extend(current);
+
+    for (name, description) in descriptions {
+        let material = description.build(images)?;
+        materials.insert(name, material);
+    }
+
+    Ok(())
+}
+
+impl MtlDescription {
+    fn build(self, images: &mut HashMap<PathBuf, Arc<Rgb32FImage>>) -> Result<Material, ObjError> {
+        let is_emissive = self
+            .emission
+            .is_some_and(|emission| emission.iter().any(|&c| c > 0.));
+
+        let is_transparent = self.dissolve.is_some_and(|dissolve| dissolve < 1.)
+            || matches!(self.illumination, Some(4 | 6 | 7 | 9));
+
+        // Illumination models 3 and 5 are raytraced reflections
+        let is_reflective = matches!(self.illumination, Some(3 | 5));
+
+        // Illumination model 2 adds a specular highlight to the diffuse color
+        let is_glossy = self.illumination == Some(2)
+            && self
+                .specular
+                .is_some_and(|specular| specular.iter().any(|&c| c > 0.));
+
+        let is_physically_based = self.roughness.is_some() || self.metallic.is_some();
+
+        let material = if is_emissive {
+            Material::DiffuseLight {
+                strength: Texture::solid(self.emission.unwrap()),
+                two_sided: true,
+                cosine_power: 0.,
+                cone: None,
+            }
+        } else if is_physically_based {
+            let value = |value: f32| Texture::solid(Rgb::broadcast(value));
+
+            let mut principled = Principled::new(self.diffuse_texture(images)?);
+            principled.refraction_index = self.refraction_index.unwrap_or(1.5);
+
+            let parameters = [
+                (self.roughness, &mut principled.roughness),
+                (self.metallic, &mut principled.metallic),
+                (self.sheen, &mut principled.sheen),
+                (self.clearcoat, &mut principled.clearcoat),
+                (
+                    self.clearcoat_roughness,
+                    &mut principled.clearcoat_roughness,
+                ),
+                (
+                    self.dissolve.map(|dissolve| 1. - dissolve),
+                    &mut principled.transmission,
+                ),
+            ];
+
+            for (parameter, texture) in parameters {
+                if let Some(parameter) = parameter {
+                    *texture = value(parameter);
+                }
+            }
+
+            Material::Principled(Arc::new(principled))
+        } else if is_transparent {
+            Material::Glass {
+                refraction_index: self.refraction_index.unwrap_or(1.5),
+                roughness: 0.,
+                tint: Rgb::white(),
+                absorption: Rgb::zero(),
+                dispersion: None,
+            }
+        } else if is_reflective {
+            let albedo = match self.specular {
+                Some(specular) if specular.iter().any(|&c| c > 0.) => Texture::solid(specular),
+                _ => self.diffuse_texture(images)?,
+            };
+
+            Material::Metal {
+                albedo,
+                roughness: self.roughness_from_exponent().unwrap_or(0.),
+                anisotropy: 0.,
+            }
+        } else if is_glossy {
+            let value = |value: f32| Texture::solid(Rgb::broadcast(value));
+            let specular = self.specular.unwrap();
+
+            let mut principled = Principled::new(self.diffuse_texture(images)?);
+            principled.specular = value(specular.sum() / 3.);
+
+            if let Some(roughness) = self.roughness_from_exponent() {
+                principled.roughness = value(roughness);
+            }
+
+            Material::Principled(Arc::new(principled))
+        } else {
+            Material::Diffuse {
+                albedo: self.diffuse_texture(images)?,
+            }
+        };
+
+        Ok(material)
+    }
+
+    /// Maps the Phong exponent to a GGX alpha, roughness is its square root. 0 is a perfect
+    /// mirror
+    fn roughness_from_exponent(&self) -> Option<f32> {
+        self.specular_exponent
+            .map(|exponent| f32::powf(2. / (exponent.max(0.) + 2.), 0.25))
+    }
+
+    fn diffuse_texture(
+        &self,
+        images: &mut HashMap<PathBuf, Arc<Rgb32FImage>>,
+    ) -> Result<Texture, ObjError> {
+        let Some(path) = &self.diffuse_map else {
+            return Ok(Texture::solid(self.diffuse.unwrap_or(Rgb::broadcast(0.8))));
+        };
+
+        if let Some(image) = images.get(path) {
+            return Ok(Texture::image(image.clone()));
+        }
+
+        let image = open_image(path, ColorSpace::Srgb)
+            .map_err(|error| ObjError::Image(path.clone(), error))?;
+
+        let image = Arc::new(image);
+        images.insert(path.clone(), image.clone());
+
+        Ok(Texture::image(image))
+    }
+}
+
+fn strip_comment(line: &str) -> &str {
+    match line.find('#') {
+        Some(index) => &line[..index],
+        None => line,
+    }
+}
+
+struct LineParser<'a> {
+    path: &'a Path,
+    line: usize,
+    tokens: SplitWhitespace<'a>,
+}
+
+impl LineParser<'_> {
+    fn error(&self, message: String) -> ObjError {
+        ObjError::Syntax {
+            path: self.path.into(),
+            line: self.line,
+            message,
+        }
+    }
+
+    fn optional_number<T: FromStr>(&mut self) -> Result<Option<T>, ObjError> {
+        match self.tokens.next() {
+            Some(token) => token
+                .parse()
+                .map(Some)
+                .map_err(|_| self.error(format!("expected a number, found \"{token}\""))),
+            None => Ok(None),
+        }
+    }
+
+    fn number<T: FromStr>(&mut self) -> Result<T, ObjError> {
+        self.optional_number()?
+            .ok_or_else(|| self.error("expected a number, found end of line".into()))
+    }
+
+    fn vec3(&mut self) -> Result<Vec3<f32>, ObjError> {
+        Ok(Vec3::new(self.number()?, self.number()?, self.number()?))
+    }
+
+    fn color(&mut self) -> Result<Rgb<f32>, ObjError> {
+        let r = self.number()?;
+
+        // A single value is used for all channels
+        match self.optional_number()? {
+            Some(g) => Ok(Rgb::new(r, g, self.number()?)),
+            None => Ok(Rgb::broadcast(r)),
+        }
+    }
+
+    fn name(&mut self) -> Result<String, ObjError> {
+        let name = self.tokens.clone().collect::<Vec<_>>().join(" ");
+
+        if name.is_empty() {
+            Err(self.error("expected a name".into()))
+        } else {
+            Ok(name)
+        }
+    }
+
+    /// Parses `v`, `v/vt`, `v//vn` or `v/vt/vn`, where indices are 1-based or negative
+    fn face_vertex(
+        &self,
+        token: &str,
+        position_count: usize,
+        uv_count: usize,
+        normal_count: usize,
+    ) -> Result<VertexIndices, ObjError> {
+        let mut parts = token.split('/');
+
+        let mut index = |kind: &str, count: usize| -> Result<Option<usize>, ObjError> {
+            let part = match parts.next() {
+                Some(part) if !part.is_empty() => part,
+                _ => return Ok(None),
+            };
+
+            let index = part
+                .parse::<isize>()
+                .map_err(|_| self.error(format!("invalid {kind} index \"{part}\"")))?;
+
+            let resolved = match index {
+                0 => None,
+                1.. => Some(index as usize - 1),
+                _ => count.checked_sub(index.unsigned_abs()),
+            };
+
+            match resolved {
+                Some(resolved) if resolved < count => Ok(Some(resolved)),
+                _ => Err(self.error(format!(
+                    "{kind} index {index} is out of bounds, there are {count} {kind}s"
+                ))),
+            }
+        };
+
+        let position = index("position", position_count)?
+            .ok_or_else(|| self.error(format!("face vertex \"{token}\" has no position")))?;
+        let uv = index("texture coordinate", uv_count)?;
+        let normal = index("normal", normal_count)?;
+
+        Ok(VertexIndices {
+            position,
+            uv,
+            normal,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Writes `files` into a directory of their own and loads the first one
+    fn load(test: &str, files: &[(&str, &str)]) -> Result<Vec<Mesh>, ObjError> {
+        let directory = std::env::temp_dir().join(format!("raytracer_obj_{test}"));
+        fs::create_dir_all(&directory).unwrap();
+
+        for (name, contents) in files {
+            fs::write(directory.join(name), contents).unwrap();
+        }
+
+        load_obj(directory.join(files[0].0), None)
+    }
+
+    /// The line of a syntax error, and the file it's in
+    fn syntax_error(result: Result<Vec<Mesh>, ObjError>) -> (String, usize) {
+        match result {
+            Err(ObjError::Syntax { path, line, .. }) => {
+                (path.file_name().unwrap().to_string_lossy().into(), line)
+            }
+            other => panic!("expected a syntax error, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn negative_indices_count_back_from_the_last_vertex() {
+        let obj = "\
+v 0 0 0
+v 1 0 0
+v 1 1 0
+f -3 -2 -1
+v 0 1 0
+f -4 -2 -1
+";
+        let meshes = load("negative_indices", &[("a.obj", obj)]).unwrap();
+
+        assert_eq!(meshes.len(), 1);
+        assert_eq!(meshes[0].bvh.objects().len(), 2);
+        assert_eq!(
+            meshes[0].data.positions,
+            [
+                Vec3::new(0., 0., 0.),
+                Vec3::new(1., 0., 0.),
+                Vec3::new(1., 1., 0.),
+                Vec3::new(0., 1., 0.),
+            ]
+        );
+    }
+
+    #[test]
+    fn negative_indices_for_texture_coordinates_and_normals() {
+        let obj = "\
+v 0 0 0
+v 1 0 0
+v 0 1 0
+vt 0 0
+vt 1 0
+vt 0 1
+vn 0 0 2
+f -3/-3/-1 -2/-2/-1 -1/-1/-1
+";
+        let meshes = load("negative_attributes", &[("a.obj", obj)]).unwrap();
+        let data = &meshes[0].data;
+
+        assert_eq!(
+            data.uvs.as_deref(),
+            Some(&[Vec2::new(0., 0.), Vec2::new(1., 0.), Vec2::new(0., 1.)][..])
+        );
+        assert_eq!(data.normals.as_deref(), Some(&[Vec3::unit_z(); 3][..]));
+    }
+
+    #[test]
+    fn out_of_bounds_indices() {
+        let obj = "v 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 2 4\n";
+        assert_eq!(
+            syntax_error(load("index_too_large", &[("a.obj", obj)])).1,
+            4
+        );
+
+        let obj = "v 0 0 0\nv 1 0 0\nv 0 1 0\nf -4 -2 -1\n";
+        assert_eq!(
+            syntax_error(load("index_too_negative", &[("a.obj", obj)])).1,
+            4
+        );
+
+        let obj = "v 0 0 0\nv 1 0 0\nv 0 1 0\nf 1/1 2/1 3/1\n";
+        assert_eq!(syntax_error(load("missing_uv", &[("a.obj", obj)])).1, 4);
+    }
+
+    #[test]
+    fn malformed_obj_lines() {
+        let vertices = "v 0 0 0\nv 1 0 0\nv 0 1 0\n";
+
+        for (test, line) in [
+            ("short_vertex", "v 1 2"),
+            ("vertex_not_a_number", "v 1 x 3"),
+            ("texture_coordinate_without_u", "vt"),
+            ("face_with_two_vertices", "f 1 2"),
+            ("face_index_not_a_number", "f 1 2/x 3"),
+            ("face_vertex_without_position", "f 1 //1 3"),
+            ("usemtl_without_name", "usemtl"),
+        ] {
+            let obj = format!("{vertices}# Comment\n\n{line}\nf 1 2 3\n");
+
+            assert_eq!(
+                syntax_error(load(test, &[("a.obj", &obj)])),
+                ("a.obj".into(), 6),
+                "{line}"
+            );
+        }
+    }
+
+    #[test]
+    fn malformed_mtl_lines() {
+        let obj = "mtllib a.mtl\nv 0 0 0\nv 1 0 0\nv 0 1 0\nusemtl red\nf 1 2 3\n";
+
+        for (test, mtl, line) in [
+            ("property_before_newmtl", "Kd 1 0 0\n", 1),
+            ("color_not_a_number", "newmtl red\nKd 1 x 0\n", 2),
+            ("color_with_two_channels", "newmtl red\nKd 1 0\n", 2),
+            ("newmtl_without_name", "newmtl\n", 1),
+            ("map_without_file", "newmtl red\nmap_Kd\n", 2),
+        ] {
+            let result = load(test, &[("a.obj", obj), ("a.mtl", mtl)]);
+
+            assert_eq!(syntax_error(result), ("a.mtl".into(), line), "{mtl}");
+        }
+    }
+
+    #[test]
+    fn glossy_mtl_materials_get_a_specular_lobe() {
+        let obj = "mtllib a.mtl\nv 0 0 0\nv 1 0 0\nv 0 1 0\nusemtl red\nf 1 2 3\n";
+        let mtl = "newmtl red\nKd 0.8 0 0\nKs 0.5 0.5 0.5\nNs 100\nillum 2\n";
+
+        let meshes = load("glossy_mtl", &[("a.obj", obj), ("a.mtl", mtl)]).unwrap();
+        let Material::Principled(principled) = &meshes[0].data.material else {
+            panic!(
+                "expected a principled material, got {:?}",
+                meshes[0].data.material
+            );
+        };
+
+        let color = |texture: &Texture| texture.color_at(Vec2::zero(), Vec3::zero());
+        assert_eq!(color(&principled.base_color), Rgb::new(0.8, 0., 0.));
+        assert_eq!(color(&principled.specular), Rgb::broadcast(0.5));
+        assert!(color(&principled.roughness).r < 0.5);
+
+        // Without a specular color illumination model 2 stays diffuse
+        let mtl = "newmtl red\nKd 0.8 0 0\nKs 0 0 0\nillum 2\n";
+        let meshes = load("matte_mtl", &[("a.obj", obj), ("a.mtl", mtl)]).unwrap();
+        assert!(matches!(meshes[0].data.material, Material::Diffuse { .. }));
+    }
+
+    #[test]
+    fn unknown_material_and_empty_files() {
+        let obj = "v 0 0 0\nv 1 0 0\nv 0 1 0\nusemtl missing\nf 1 2 3\n";
+        assert!(matches!(
+            load("unknown_material", &[("a.obj", obj)]),
+            Err(ObjError::UnknownMaterial(_, name)) if name == "missing"
+        ));
+
+        let obj = "# Only vertices\nv 0 0 0\n";
+        assert!(matches!(
+            load("no_faces", &[("a.obj", obj)]),
+            Err(ObjError::Empty(_))
+        ));
+    }
+}
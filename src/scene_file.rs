@@ -0,0 +1,1180 @@
+use crate::background::{Background, EnvironmentMap};
+use crate::bvh::BvhNode;
+use crate::camera::Camera;
+use crate::data::Hittable;
+use crate::image_io::{open_image, ColorSpace};
+use crate::lights::{Cone, Light};
+use crate::linear_bvh::LinearBvh;
+use crate::materials::{ConductorPreset, Dispersion, Material, Principled};
+use crate::obj::{load_obj, ObjError};
+use crate::shapes::constant_medium::ConstantMedium;
+use crate::shapes::cuboid::Cuboid;
+use crate::shapes::instance::Instance;
+use crate::shapes::mesh::Mesh;
+use crate::shapes::quad::{Quad, QuadShape};
+use crate::shapes::sphere::Sphere;
+use crate::shapes::triangle::Triangle;
+use crate::sky::Sky;
+use crate::texture::{Noise, Texture};
+use crate::Scene;
+use image::{ImageError, Rgb32FImage};
+use noise::{Fbm, MultiFractal, Perlin, Turbulence};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt::{self, Display};
+use std::ops::RangeInclusive;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::{fs, io};
+use vek::{Mat4, Rgb, Vec2, Vec3};
+
+#[derive(Debug)]
+pub enum SceneFileError {
+    Io(PathBuf, io::Error),
+    UnsupportedFormat(PathBuf),
+    Parse(PathBuf, String),
+    Image(PathBuf, ImageError),
+    UnknownMaterial(String),
+    InvalidMesh(String),
+    Obj(ObjError),
+    UnknownObject(String),
+    RecursiveObject(String),
+    InvalidShutter(f32, f32),
+    InvalidSunSize(f32),
+    InvalidDensity(f32),
+}
+
+impl Display for SceneFileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SceneFileError::Io(path, error) => {
+                write!(f, "failed to read {}: {error}", path.display())
+            }
+            SceneFileError::UnsupportedFormat(path) => write!(
+                f,
+                "unsupported scene format for {}, expected .toml, .json or .ron",
+                path.display()
+            ),
+            SceneFileError::Parse(path, error) => {
+                write!(f, "failed to parse {}: {error}", path.display())
+            }
+            SceneFileError::Image(path, error) => {
+                write!(f, "failed to load image {}: {error}", path.display())
+            }
+            SceneFileError::UnknownMaterial(name) => write!(f, "unknown material \"{name}\""),
+            SceneFileError::InvalidMesh(reason) => write!(f, "invalid mesh: {reason}"),
+            SceneFileError::Obj(error) => error.fmt(f),
+            SceneFileError::UnknownObject(name) => write!(f, "unknown or empty object \"{name}\""),
+            SceneFileError::RecursiveObject(name) => {
+                write!(f, "object \"{name}\" contains an instance of itself")
+            }
+            SceneFileError::InvalidShutter(open, close) => write!(
+                f,
+                "shutter opens at {open} and closes at {close}, expected 0 <= open <= close <= 1"
+            ),
+            SceneFileError::InvalidSunSize(size) => write!(
+                f,
+                "sun_size {size} is outside {}..={} degrees",
+                SUN_SIZES.start(),
+                SUN_SIZES.end()
+            ),
+            SceneFileError::InvalidDensity(density) => {
+                write!(
+                    f,
+                    "density {density} of a constant medium isn't a finite number above 0"
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for SceneFileError {}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SceneFile {
+    pub camera: CameraDescription,
+
+    /// Materials that shapes can refer to by name
+    #[serde(default)]
+    pub materials: HashMap<String, MaterialDescription>,
+
+    /// Groups of shapes that can be placed several times with `instance`
+    #[serde(default)]
+    pub objects: HashMap<String, Vec<ShapeEntry>>,
+
+    #[serde(default)]
+    pub shapes: Vec<ShapeEntry>,
+
+    #[serde(default)]
+    pub lights: Vec<LightDescription>,
+
+    #[serde(default)]
+    pub background: Option<BackgroundDescription>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum BackgroundDescription {
+    Color {
+        color: [f32; 3],
+    },
+
+    /// Equirectangular image, usually HDR or EXR, with its middle towards -z
+    Environment {
+        path: PathBuf,
+        /// Around the y axis, in degrees
+        #[serde(default)]
+        rotation: f32,
+        #[serde(default = "default_strength")]
+        strength: f32,
+    },
+
+    /// Daylight for a sun towards `sun_direction`
+    Sky {
+        sun_direction: [f32; 3],
+        /// Haziness, from 2 for a clear day to around 10
+        #[serde(default = "default_turbidity")]
+        turbidity: f32,
+        /// Angular diameter of the sun in degrees, larger suns give softer shadows. Within
+        /// `SUN_SIZES`
+        #[serde(default = "default_sun_size")]
+        sun_size: f32,
+        #[serde(default = "default_strength")]
+        strength: f32,
+    },
+}
+
+fn default_turbidity() -> f32 {
+    3.
+}
+
+/// Smaller suns round to a disk of no size in f32
+const SUN_SIZES: RangeInclusive<f32> = 0.1..=180.;
+
+fn default_sun_size() -> f32 {
+    0.545
+}
+
+fn default_strength() -> f32 {
+    1.
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CameraDescription {
+    pub position: [f32; 3],
+    pub target: [f32; 3],
+    #[serde(default = "default_up")]
+    pub up: [f32; 3],
+
+    /// Used when the scene has no `background`
+    #[serde(default)]
+    pub background_color: [f32; 3],
+
+    /// In degrees
+    pub vertical_fov: f32,
+    /// In degrees
+    #[serde(default)]
+    pub defocus_angle: f32,
+    #[serde(default = "default_focus_distance")]
+    pub focus_distance: f32,
+
+    /// Shapes with motion move from their start position at time 0 to their end position
+    /// at time 1, so the shutter has to stay within that
+    #[serde(default)]
+    pub shutter_open: f32,
+    #[serde(default)]
+    pub shutter_close: f32,
+}
+
+fn default_up() -> [f32; 3] {
+    [0., 1., 0.]
+}
+
+fn default_focus_distance() -> f32 {
+    1.
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum TextureDescription {
+    Solid {
+        color: [f32; 3],
+    },
+
+    Checker {
+        even: [f32; 3],
+        odd: [f32; 3],
+        scale: f32,
+    },
+
+    Noise {
+        noise: NoiseDescription,
+        scale: f32,
+    },
+
+    /// The path is relative to the scene file
+    Image {
+        path: PathBuf,
+    },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum NoiseDescription {
+    Perlin {
+        #[serde(default)]
+        seed: u32,
+    },
+
+    Turbulence {
+        #[serde(default)]
+        seed: u32,
+    },
+
+    Fbm {
+        #[serde(default)]
+        seed: u32,
+        #[serde(default = "default_octaves")]
+        octaves: usize,
+    },
+}
+
+fn default_octaves() -> usize {
+    Fbm::<Perlin>::DEFAULT_OCTAVE_COUNT
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum MaterialDescription {
+    Diffuse {
+        albedo: TextureDescription,
+    },
+    Metal {
+        albedo: TextureDescription,
+        /// Older scenes call it fuzz
+        #[serde(default, alias = "fuzz")]
+        roughness: f32,
+        #[serde(default)]
+        anisotropy: f32,
+    },
+    Conductor {
+        ior: ConductorIor,
+        #[serde(default)]
+        roughness: f32,
+        #[serde(default)]
+        anisotropy: f32,
+    },
+    /// Without a `refraction_index`, dispersive glass uses its index at 550 nm for paths
+    /// that don't carry a single wavelength, and other glass uses 1.5
+    Glass {
+        #[serde(default)]
+        refraction_index: Option<f32>,
+        #[serde(default)]
+        roughness: f32,
+        #[serde(default = "default_tint")]
+        tint: [f32; 3],
+        /// Per unit of distance inside the glass
+        #[serde(default)]
+        absorption: [f32; 3],
+        #[serde(default)]
+        dispersion: Option<DispersionDescription>,
+    },
+    DiffuseLight {
+        strength: TextureDescription,
+        /// Otherwise only the front face emits, the side the normal of quads points to
+        #[serde(default = "default_two_sided")]
+        two_sided: bool,
+        #[serde(default)]
+        cosine_power: f32,
+        /// Around the normal
+        #[serde(default)]
+        cone: Option<ConeDescription>,
+    },
+    Isotropic {
+        albedo: TextureDescription,
+    },
+
+    Principled(Box<PrincipledDescription>),
+}
+
+fn default_two_sided() -> bool {
+    true
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct ConeDescription {
+    /// From the axis to the edge, in degrees
+    pub angle: f32,
+    /// Fraction of the angle over which the light fades out
+    #[serde(default)]
+    pub blend: f32,
+}
+
+impl From<ConeDescription> for Cone {
+    fn from(cone: ConeDescription) -> Self {
+        Cone {
+            angle: cone.angle.to_radians(),
+            blend: cone.blend,
+        }
+    }
+}
+
+/// Lights that aren't shapes, sampled directly instead
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum LightDescription {
+    Point {
+        position: [f32; 3],
+        intensity: [f32; 3],
+    },
+    Spot {
+        position: [f32; 3],
+        direction: [f32; 3],
+        intensity: [f32; 3],
+        cone: ConeDescription,
+    },
+    /// `direction` is where the light travels towards
+    Directional {
+        direction: [f32; 3],
+        irradiance: [f32; 3],
+    },
+}
+
+impl From<LightDescription> for Light {
+    fn from(light: LightDescription) -> Self {
+        match light {
+            LightDescription::Point {
+                position,
+                intensity,
+            } => Light::Point {
+                position: Vec3::from(position),
+                intensity: Rgb::from(intensity),
+            },
+            LightDescription::Spot {
+                position,
+                direction,
+                intensity,
+                cone,
+            } => Light::Spot {
+                position: Vec3::from(position),
+                direction: Vec3::from(direction),
+                intensity: Rgb::from(intensity),
+                cone: Cone::from(cone),
+            },
+            LightDescription::Directional {
+                direction,
+                irradiance,
+            } => Light::Directional {
+                direction: Vec3::from(direction),
+                irradiance: Rgb::from(irradiance),
+            },
+        }
+    }
+}
+
+fn default_tint() -> [f32; 3] {
+    [1., 1., 1.]
+}
+
+/// Coefficients for wavelengths in micrometers
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum DispersionDescription {
+    Cauchy { a: f32, b: f32 },
+    Sellmeier { b: [f32; 3], c: [f32; 3] },
+}
+
+impl From<DispersionDescription> for Dispersion {
+    fn from(dispersion: DispersionDescription) -> Self {
+        match dispersion {
+            DispersionDescription::Cauchy { a, b } => Dispersion::Cauchy { a, b },
+            DispersionDescription::Sellmeier { b, c } => Dispersion::Sellmeier { b, c },
+        }
+    }
+}
+
+/// Unset parameters keep the defaults of `Principled::new`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PrincipledDescription {
+    pub base_color: InputDescription,
+    #[serde(default)]
+    pub metallic: Option<InputDescription>,
+    #[serde(default)]
+    pub roughness: Option<InputDescription>,
+    #[serde(default)]
+    pub specular: Option<InputDescription>,
+    #[serde(default)]
+    pub sheen: Option<InputDescription>,
+    #[serde(default)]
+    pub clearcoat: Option<InputDescription>,
+    #[serde(default)]
+    pub clearcoat_roughness: Option<InputDescription>,
+    #[serde(default)]
+    pub transmission: Option<InputDescription>,
+    #[serde(default)]
+    pub refraction_index: Option<f32>,
+    #[serde(default)]
+    pub anisotropy: Option<f32>,
+}
+
+/// A material parameter given as a number, a color or any texture
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum InputDescription {
+    Value(f32),
+    Color([f32; 3]),
+    Texture(TextureDescription),
+}
+
+/// Either a measured metal, or the real and imaginary parts of the index of refraction
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum ConductorIor {
+    Preset(ConductorPresetDescription),
+    Custom { eta: [f32; 3], k: [f32; 3] },
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConductorPresetDescription {
+    Gold,
+    Copper,
+    #[serde(alias = "aluminum")]
+    Aluminium,
+    Silver,
+}
+
+impl From<ConductorPresetDescription> for ConductorPreset {
+    fn from(preset: ConductorPresetDescription) -> Self {
+        match preset {
+            ConductorPresetDescription::Gold => ConductorPreset::Gold,
+            ConductorPresetDescription::Copper => ConductorPreset::Copper,
+            ConductorPresetDescription::Aluminium => ConductorPreset::Aluminium,
+            ConductorPresetDescription::Silver => ConductorPreset::Silver,
+        }
+    }
+}
+
+/// Either the name of an entry in `materials`, or a material written inline
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum MaterialReference {
+    Named(String),
+    Inline(MaterialDescription),
+}
+
+/// A single step of a transform, steps are applied in order
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TransformStep {
+    Translate([f32; 3]),
+    Scale([f32; 3]),
+    /// In degrees
+    RotateX(f32),
+    /// In degrees
+    RotateY(f32),
+    /// In degrees
+    RotateZ(f32),
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum QuadShapeDescription {
+    #[default]
+    Parallelogram,
+    Triangle,
+    Ellipse,
+}
+
+impl From<QuadShapeDescription> for QuadShape {
+    fn from(shape: QuadShapeDescription) -> Self {
+        match shape {
+            QuadShapeDescription::Parallelogram => QuadShape::Parallelogram,
+            QuadShapeDescription::Triangle => QuadShape::Triangle,
+            QuadShapeDescription::Ellipse => QuadShape::Ellipse,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ShapeDescription {
+    Sphere {
+        center: [f32; 3],
+        /// Where the center moves to at time 1, for motion blur
+        #[serde(default)]
+        end_center: Option<[f32; 3]>,
+        radius: f32,
+        material: MaterialReference,
+    },
+
+    /// For ellipses `origin` is the center and `u` and `v` are the semi-axes
+    Quad {
+        origin: [f32; 3],
+        u: [f32; 3],
+        v: [f32; 3],
+        #[serde(default)]
+        shape: QuadShapeDescription,
+        material: MaterialReference,
+    },
+
+    Disk {
+        center: [f32; 3],
+        normal: [f32; 3],
+        radius: f32,
+        material: MaterialReference,
+    },
+
+    Box {
+        a: [f32; 3],
+        b: [f32; 3],
+        material: MaterialReference,
+    },
+
+    Triangle {
+        vertices: [[f32; 3]; 3],
+        material: MaterialReference,
+    },
+
+    Mesh {
+        positions: Vec<[f32; 3]>,
+        indices: Vec<[u32; 3]>,
+        #[serde(default)]
+        normals: Option<Vec<[f32; 3]>>,
+        #[serde(default)]
+        uvs: Option<Vec<[f32; 2]>>,
+        material: MaterialReference,
+    },
+
+    /// A Wavefront OBJ file, the path is relative to the scene file
+    Obj {
+        path: PathBuf,
+        /// Replaces the materials from the MTL files
+        #[serde(default)]
+        material: Option<MaterialReference>,
+    },
+
+    /// Places an entry from `objects`, the geometry is shared between all instances
+    Instance { object: String },
+
+    /// Smoke or fog filling a convex boundary shape
+    ConstantMedium {
+        boundary: Box<ShapeEntry>,
+        /// Above 0, higher is thicker
+        density: f32,
+        albedo: TextureDescription,
+    },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ShapeEntry {
+    #[serde(flatten)]
+    pub shape: ShapeDescription,
+
+    /// Applied to the shape through an instance
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub transform: Vec<TransformStep>,
+
+    /// Translation reached at time 1 after the transform, for motion blur
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub motion: Option<[f32; 3]>,
+}
+
+impl SceneFile {
+    /// Parses a scene file, the format is picked from the file extension
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, SceneFileError> {
+        let path = path.as_ref();
+        let source =
+            fs::read_to_string(path).map_err(|error| SceneFileError::Io(path.into(), error))?;
+
+        let parse_error = |error: String| SceneFileError::Parse(path.into(), error);
+
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("toml") => toml::from_str(&source).map_err(|error| parse_error(error.to_string())),
+            Some("json") => {
+                serde_json::from_str(&source).map_err(|error| parse_error(error.to_string()))
+            }
+            Some("ron") => ron::from_str(&source).map_err(|error| parse_error(error.to_string())),
+            _ => Err(SceneFileError::UnsupportedFormat(path.into())),
+        }
+    }
+
+    /// Builds the scene, relative paths are resolved from `directory`
+    pub fn build(&self, directory: impl AsRef<Path>) -> Result<Scene, SceneFileError> {
+        let mut builder = SceneBuilder {
+            directory: directory.as_ref(),
+            scene_file: self,
+            materials: HashMap::new(),
+            images: HashMap::new(),
+            objects: HashMap::new(),
+            objects_in_progress: Vec::new(),
+        };
+
+        builder.build()
+    }
+}
+
+/// Parses and builds the scene file at `path`
+pub fn load_scene(path: impl AsRef<Path>) -> Result<Scene, SceneFileError> {
+    let path = path.as_ref();
+    let directory = path.parent().unwrap_or(Path::new("."));
+
+    SceneFile::open(path)?.build(directory)
+}
+
+struct SceneBuilder<'a> {
+    directory: &'a Path,
+    scene_file: &'a SceneFile,
+
+    materials: HashMap<String, Material>,
+    images: HashMap<(PathBuf, ColorSpace), Arc<Rgb32FImage>>,
+    objects: HashMap<String, Vec<Arc<dyn Hittable>>>,
+
+    /// Names of the objects currently being built, to catch objects containing themselves
+    objects_in_progress: Vec<String>,
+}
+
+/// Puts several objects behind a bvh, so they can be handled as one
+fn group(mut objects: Vec<Arc<dyn Hittable>>) -> Arc<dyn Hittable> {
+    if objects.len() == 1 {
+        return objects.pop().unwrap();
+    }
+
+    let bvh = BvhNode::new_sah(&objects).unwrap();
+
+    Arc::new(LinearBvh::from(bvh))
+}
+
+/// Like `group`, but leaves the lights out of the bvh so the integrator can still sample them
+fn group_except_lights(objects: Vec<Arc<dyn Hittable>>) -> Vec<Arc<dyn Hittable>> {
+    let (mut lights, others): (Vec<_>, Vec<_>) =
+        objects.into_iter().partition(|object| object.is_light());
+
+    if !others.is_empty() {
+        lights.push(group(others));
+    }
+
+    lights
+}
+
+impl SceneBuilder<'_> {
+    fn build(&mut self) -> Result<Scene, SceneFileError> {
+        let mut scene = Scene {
+            camera: self.scene_file.camera.build()?,
+            ..Default::default()
+        };
+
+        for shape in &self.scene_file.shapes {
+            let objects = self.shape(shape)?;
+            scene.objects.extend(objects);
+        }
+
+        scene.lights = self
+            .scene_file
+            .lights
+            .iter()
+            .map(|&light| Light::from(light))
+            .collect();
+
+        scene.background = match &self.scene_file.background {
+            None => Background::Color(Rgb::from(self.scene_file.camera.background_color)),
+
+            Some(BackgroundDescription::Color { color }) => Background::Color(Rgb::from(*color)),
+
+            Some(BackgroundDescription::Environment {
+                path,
+                rotation,
+                strength,
+            }) => Background::Environment(Arc::new(EnvironmentMap::new(
+                self.image(path, ColorSpace::Srgb)?,
+                rotation.to_radians(),
+                *strength,
+            ))),
+
+            &Some(BackgroundDescription::Sky {
+                sun_direction,
+                turbidity,
+                sun_size,
+                strength,
+            }) => {
+                if !SUN_SIZES.contains(&sun_size) {
+                    return Err(SceneFileError::InvalidSunSize(sun_size));
+                }
+
+                Background::Sky(Arc::new(Sky::new(
+                    Vec3::from(sun_direction),
+                    turbidity,
+                    sun_size.to_radians(),
+                    strength,
+                )))
+            }
+        };
+
+        Ok(scene)
+    }
+
+    fn shape(&mut self, shape: &ShapeEntry) -> Result<Vec<Arc<dyn Hittable>>, SceneFileError> {
+        let mut objects = Vec::<Arc<dyn Hittable>>::new();
+
+        match &shape.shape {
+            ShapeDescription::Sphere {
+                center,
+                end_center,
+                radius,
+                material,
+            } => {
+                let material = self.material(material)?;
+
+                objects.push(Arc::new(Sphere::moving(
+                    Vec3::from(*center),
+                    Vec3::from(end_center.unwrap_or(*center)),
+                    *radius,
+                    material,
+                )));
+            }
+
+            ShapeDescription::Quad {
+                origin,
+                u,
+                v,
+                shape,
+                material,
+            } => {
+                let material = self.material(material)?;
+
+                objects.push(Arc::new(Quad::with_shape(
+                    Vec3::from(*origin),
+                    Vec3::from(*u),
+                    Vec3::from(*v),
+                    QuadShape::from(*shape),
+                    material,
+                )));
+            }
+
+            ShapeDescription::Disk {
+                center,
+                normal,
+                radius,
+                material,
+            } => {
+                let material = self.material(material)?;
+
+                objects.push(Arc::new(Quad::disk(
+                    Vec3::from(*center),
+                    Vec3::from(*normal),
+                    *radius,
+                    material,
+                )));
+            }
+
+            ShapeDescription::Box { a, b, material } => {
+                let material = self.material(material)?;
+
+                objects.push(Arc::new(Cuboid::new(
+                    Vec3::from(*a),
+                    Vec3::from(*b),
+                    material,
+                )));
+            }
+
+            ShapeDescription::Triangle { vertices, material } => {
+                let material = self.material(material)?;
+                let [a, b, c] = vertices.map(Vec3::from);
+
+                objects.push(Arc::new(Triangle::new(a, b, c, material)));
+            }
+
+            ShapeDescription::Mesh {
+                positions,
+                indices,
+                normals,
+                uvs,
+                material,
+            } => {
+                validate_mesh(positions, indices, normals, uvs)?;
+
+                let material = self.material(material)?;
+
+                let positions = positions.iter().copied().map(Vec3::from).collect();
+                let normals = normals
+                    .as_ref()
+                    .map(|normals| normals.iter().copied().map(Vec3::from).collect());
+                let uvs = uvs
+                    .as_ref()
+                    .map(|uvs| uvs.iter().copied().map(Vec2::from).collect());
+
+                let mesh = Mesh::new(positions, indices, normals, uvs, material)
+                    .ok_or_else(|| SceneFileError::InvalidMesh("no triangles".into()))?;
+
+                objects.push(Arc::new(mesh));
+            }
+
+            ShapeDescription::Obj { path, material } => {
+                let material = material
+                    .as_ref()
+                    .map(|material| self.material(material))
+                    .transpose()?;
+
+                let meshes =
+                    load_obj(self.directory.join(path), material).map_err(SceneFileError::Obj)?;
+
+                objects.extend(meshes.into_iter().map(|mesh| Arc::new(mesh) as _));
+            }
+
+            ShapeDescription::Instance { object } => objects.extend(self.object(object)?),
+
+            ShapeDescription::ConstantMedium {
+                boundary,
+                density,
+                albedo,
+            } => {
+                if !(density.is_finite() && *density > 0.) {
+                    return Err(SceneFileError::InvalidDensity(*density));
+                }
+
+                let boundary = group(self.shape(boundary)?);
+                let albedo = self.texture(albedo, ColorSpace::Srgb)?;
+
+                objects.push(Arc::new(ConstantMedium::new(boundary, *density, albedo)));
+            }
+        }
+
+        if shape.transform.is_empty() && shape.motion.is_none() {
+            return Ok(objects);
+        }
+
+        let transform = build_transform(&shape.transform);
+        let motion = Vec3::from(shape.motion.unwrap_or_default());
+
+        Ok(group_except_lights(objects)
+            .into_iter()
+            .map(|object| Arc::new(Instance::moving(object, transform, motion)) as _)
+            .collect())
+    }
+
+    /// Builds a named object once, every instance of it shares the result
+    fn object(&mut self, name: &str) -> Result<Vec<Arc<dyn Hittable>>, SceneFileError> {
+        if let Some(objects) = self.objects.get(name) {
+            return Ok(objects.clone());
+        }
+
+        if self.objects_in_progress.iter().any(|other| other == name) {
+            return Err(SceneFileError::RecursiveObject(name.into()));
+        }
+
+        let shapes = self
+            .scene_file
+            .objects
+            .get(name)
+            .ok_or_else(|| SceneFileError::UnknownObject(name.into()))?;
+
+        self.objects_in_progress.push(name.into());
+
+        let mut objects = Vec::new();
+        for shape in shapes {
+            objects.extend(self.shape(shape)?);
+        }
+
+        self.objects_in_progress.pop();
+
+        if objects.is_empty() {
+            return Err(SceneFileError::UnknownObject(name.into()));
+        }
+
+        let objects = group_except_lights(objects);
+        self.objects.insert(name.into(), objects.clone());
+
+        Ok(objects)
+    }
+
+    fn material(&mut self, reference: &MaterialReference) -> Result<Material, SceneFileError> {
+        match reference {
+            MaterialReference::Named(name) => {
+                if let Some(material) = self.materials.get(name) {
+                    return Ok(material.clone());
+                }
+
+                let description = self
+                    .scene_file
+                    .materials
+                    .get(name)
+                    .ok_or_else(|| SceneFileError::UnknownMaterial(name.clone()))?;
+
+                let material = self.build_material(description)?;
+                self.materials.insert(name.clone(), material.clone());
+
+                Ok(material)
+            }
+
+            MaterialReference::Inline(description) => self.build_material(description),
+        }
+    }
+
+    fn build_material(
+        &mut self,
+        description: &MaterialDescription,
+    ) -> Result<Material, SceneFileError> {
+        let material = match description {
+            MaterialDescription::Diffuse { albedo } => Material::Diffuse {
+                albedo: self.texture(albedo, ColorSpace::Srgb)?,
+            },
+
+            MaterialDescription::Metal {
+                albedo,
+                roughness,
+                anisotropy,
+            } => Material::Metal {
+                albedo: self.texture(albedo, ColorSpace::Srgb)?,
+                roughness: *roughness,
+                anisotropy: *anisotropy,
+            },
+
+            &MaterialDescription::Conductor {
+                ior,
+                roughness,
+                anisotropy,
+            } => {
+                let (eta, k) = match ior {
+                    ConductorIor::Preset(preset) => ConductorPreset::from(preset).ior(),
+                    ConductorIor::Custom { eta, k } => (Rgb::from(eta), Rgb::from(k)),
+                };
+
+                Material::Conductor {
+                    eta,
+                    k,
+                    roughness,
+                    anisotropy,
+                }
+            }
+
+            &MaterialDescription::Glass {
+                refraction_index,
+                roughness,
+                tint,
+                absorption,
+                dispersion,
+            } => {
+                let dispersion = dispersion.map(Dispersion::from);
+
+                let refraction_index = refraction_index.unwrap_or_else(|| {
+                    dispersion.map_or(1.5, |dispersion| dispersion.refraction_index(550.))
+                });
+
+                Material::Glass {
+                    refraction_index,
+                    roughness,
+                    tint: Rgb::from(tint),
+                    absorption: Rgb::from(absorption),
+                    dispersion,
+                }
+            }
+
+            MaterialDescription::DiffuseLight {
+                strength,
+                two_sided,
+                cosine_power,
+                cone,
+            } => Material::DiffuseLight {
+                strength: self.texture(strength, ColorSpace::Srgb)?,
+                two_sided: *two_sided,
+                cosine_power: *cosine_power,
+                cone: cone.map(Cone::from),
+            },
+
+            MaterialDescription::Isotropic { albedo } => Material::Isotropic {
+                albedo: self.texture(albedo, ColorSpace::Srgb)?,
+            },
+
+            MaterialDescription::Principled(description) => {
+                let PrincipledDescription {
+                    base_color,
+                    metallic,
+                    roughness,
+                    specular,
+                    sheen,
+                    clearcoat,
+                    clearcoat_roughness,
+                    transmission,
+                    refraction_index,
+                    anisotropy,
+                } = description.as_ref();
+
+                let mut principled = Principled::new(self.input(base_color, ColorSpace::Srgb)?);
+
+                let parameters = [
+                    (metallic, &mut principled.metallic),
+                    (roughness, &mut principled.roughness),
+                    (specular, &mut principled.specular),
+                    (sheen, &mut principled.sheen),
+                    (clearcoat, &mut principled.clearcoat),
+                    (clearcoat_roughness, &mut principled.clearcoat_roughness),
+                    (transmission, &mut principled.transmission),
+                ];
+
+                for (description, texture) in parameters {
+                    if let Some(description) = description {
+                        *texture = self.input(description, ColorSpace::Linear)?;
+                    }
+                }
+
+                principled.refraction_index =
+                    refraction_index.unwrap_or(principled.refraction_index);
+                principled.anisotropy = anisotropy.unwrap_or(principled.anisotropy);
+
+                Material::Principled(Arc::new(principled))
+            }
+        };
+
+        Ok(material)
+    }
+
+    /// `color_space` is what images mean, colors or data
+    fn input(
+        &mut self,
+        description: &InputDescription,
+        color_space: ColorSpace,
+    ) -> Result<Texture, SceneFileError> {
+        match description {
+            &InputDescription::Value(value) => Ok(Texture::solid(Rgb::broadcast(value))),
+            &InputDescription::Color(color) => Ok(Texture::solid(Rgb::from(color))),
+            InputDescription::Texture(description) => self.texture(description, color_space),
+        }
+    }
+
+    fn texture(
+        &mut self,
+        description: &TextureDescription,
+        color_space: ColorSpace,
+    ) -> Result<Texture, SceneFileError> {
+        let texture = match description {
+            &TextureDescription::Solid { color } => Texture::solid(Rgb::from(color)),
+
+            &TextureDescription::Checker { even, odd, scale } => {
+                Texture::checker(Rgb::from(even), Rgb::from(odd), scale)
+            }
+
+            TextureDescription::Noise { noise, scale } => Texture::noise(noise.build(), *scale),
+
+            TextureDescription::Image { path } => Texture::image(self.image(path, color_space)?),
+        };
+
+        Ok(texture)
+    }
+
+    /// Loads images once, even when several textures use them
+    fn image(
+        &mut self,
+        path: &Path,
+        color_space: ColorSpace,
+    ) -> Result<Arc<Rgb32FImage>, SceneFileError> {
+        let path = self.directory.join(path);
+
+        if let Some(image) = self.images.get(&(path.clone(), color_space)) {
+            return Ok(image.clone());
+        }
+
+        let image = open_image(&path, color_space)
+            .map_err(|error| SceneFileError::Image(path.clone(), error))?;
+
+        let image = Arc::new(image);
+        self.images.insert((path, color_space), image.clone());
+
+        Ok(image)
+    }
+}
+
+impl CameraDescription {
+    fn build(&self) -> Result<Camera, SceneFileError> {
+        let (open, close) = (self.shutter_open, self.shutter_close);
+        if !(0. <= open && open <= close && close <= 1.) {
+            return Err(SceneFileError::InvalidShutter(open, close));
+        }
+
+        Ok(Camera {
+            position: Vec3::from(self.position),
+            target: Vec3::from(self.target),
+            up: Vec3::from(self.up),
+
+            vertical_fov: self.vertical_fov.to_radians(),
+            defocus_angle: self.defocus_angle.to_radians(),
+            focus_distance: self.focus_distance,
+
+            shutter_open: self.shutter_open,
+            shutter_close: self.shutter_close,
+        })
+    }
+}
+
+impl NoiseDescription {
+    fn build(&self) -> Arc<dyn Noise> {
+        match *self {
+            NoiseDescription::Perlin { seed } => Arc::new(Perlin::new(seed)),
+
+            NoiseDescription::Turbulence { seed } => {
+                Arc::new(Turbulence::<_, Perlin>::new(Perlin::new(seed)))
+            }
+
+            NoiseDescription::Fbm { seed, octaves } => {
+                Arc::new(Fbm::<Perlin>::new(seed).set_octaves(octaves))
+            }
+        }
+    }
+}
+
+fn build_transform(steps: &[TransformStep]) -> Mat4<f32> {
+    steps
+        .iter()
+        .fold(Mat4::identity(), |matrix, step| match *step {
+            TransformStep::Translate(offset) => matrix.translated_3d(Vec3::from(offset)),
+            TransformStep::Scale(scale) => matrix.scaled_3d(Vec3::from(scale)),
+            TransformStep::RotateX(angle) => matrix.rotated_x(angle.to_radians()),
+            TransformStep::RotateY(angle) => matrix.rotated_y(angle.to_radians()),
+            TransformStep::RotateZ(angle) => matrix.rotated_z(angle.to_radians()),
+        })
+}
+
+fn validate_mesh(
+    positions: &[[f32; 3]],
+    indices: &[[u32; 3]],
+    normals: &Option<Vec<[f32; 3]>>,
+    uvs: &Option<Vec<[f32; 2]>>,
+) -> Result<(), SceneFileError> {
+    let invalid = |reason: String| Err(SceneFileError::InvalidMesh(reason));
+
+    if indices.is_empty() {
+        return invalid("no triangles".into());
+    }
+
+    if let Some(index) = indices
+        .iter()
+        .flatten()
+        .find(|&&index| index as usize >= positions.len())
+    {
+        return invalid(format!(
+            "index {index} is out of bounds for {} positions",
+            positions.len()
+        ));
+    }
+
+    if let Some(normals) = normals {
+        if normals.len() != positions.len() {
+            return invalid(format!(
+                "{} normals given for {} positions",
+                normals.len(),
+                positions.len()
+            ));
+        }
+    }
+
+    if let Some(uvs) = uvs {
+        if uvs.len() != positions.len() {
+            return invalid(format!(
+                "{} uvs given for {} positions",
+                uvs.len(),
+                positions.len()
+            ));
+        }
+    }
+
+    Ok(())
+}
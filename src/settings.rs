@@ -0,0 +1,108 @@
+use crate::bvh::BvhBuilder;
+use crate::tiles::{Tile, TileOrder};
+use clap::Parser;
+use rand::{rngs::SmallRng, Rng, SeedableRng};
+use vek::Vec2;
+
+#[derive(Debug, Clone, Copy, Parser)]
+pub struct RenderSettings {
+    /// Width of the image in pixels
+    #[arg(long, default_value_t = 600)]
+    pub width: u32,
+
+    /// Width divided by height, the height is derived from this
+    #[arg(long, default_value_t = 1.)]
+    pub aspect_ratio: f32,
+
+    /// Amount of rays traced per pixel
+    #[arg(long = "samples", default_value_t = 10000)]
+    pub samples_per_pixel: u32,
+
+    /// Samples per pixel in each pass of a progressive render, all of them in one pass if
+    /// not set
+    #[arg(long)]
+    pub pass_samples: Option<u32>,
+
+    /// Stops sampling pixels once the standard error of their luminance falls below this
+    /// fraction of it, `--samples` becomes the most a pixel gets. 0.01 is hard to tell
+    /// apart from a converged render
+    #[arg(long)]
+    pub adaptive_threshold: Option<f32>,
+
+    /// Hard cap on bounces per ray, most paths end earlier through Russian roulette
+    #[arg(long, default_value_t = 100)]
+    pub max_depth: u32,
+
+    /// Seed for the random number generators, random if not set
+    #[arg(long)]
+    pub seed: Option<u64>,
+
+    /// Amount of threads to render with, all cores if not set
+    #[arg(long = "threads")]
+    pub thread_count: Option<usize>,
+
+    /// Width and height of the tiles the image is split into for the threads
+    #[arg(long, default_value_t = 32)]
+    pub tile_size: u32,
+
+    /// In which order tiles are rendered
+    #[arg(long, value_enum, default_value_t)]
+    pub tile_order: TileOrder,
+
+    /// Only renders the pixels inside `x,y,width,height`, the rest of the image stays black
+    #[arg(long)]
+    pub region: Option<Tile>,
+
+    /// How the bvh over the scene is built
+    #[arg(long = "bvh", value_enum, default_value_t)]
+    pub bvh_builder: BvhBuilder,
+}
+
+impl Default for RenderSettings {
+    fn default() -> Self {
+        Self {
+            width: 600,
+            aspect_ratio: 1.,
+            samples_per_pixel: 10000,
+            pass_samples: None,
+            adaptive_threshold: None,
+            max_depth: 100,
+            seed: None,
+            thread_count: None,
+            tile_size: 32,
+            tile_order: TileOrder::default(),
+            region: None,
+            bvh_builder: BvhBuilder::default(),
+        }
+    }
+}
+
+impl RenderSettings {
+    pub fn image_size(&self) -> Vec2<u32> {
+        let height = (self.width as f32 / self.aspect_ratio).round() as u32;
+
+        Vec2::new(self.width, height.max(1))
+    }
+
+    /// The part of the image that's rendered
+    pub fn region(&self) -> Tile {
+        let image_size = self.image_size();
+
+        match self.region {
+            Some(region) => region.clamp(image_size),
+            None => Tile::new(Vec2::zero(), image_size),
+        }
+    }
+
+    /// The seed, or one from entropy if not set. Drawn once per render, every rng of the
+    /// render is derived from it with `stream_rng`
+    pub fn base_seed(&self) -> u64 {
+        self.seed.unwrap_or_else(|| SmallRng::from_entropy().gen())
+    }
+}
+
+/// Creates a rng from a render's base seed, `stream` makes it possible to derive independent
+/// rngs
+pub fn stream_rng(seed: u64, stream: u64) -> SmallRng {
+    SmallRng::seed_from_u64(seed ^ stream.wrapping_mul(0x9e37_79b9_7f4a_7c15))
+}
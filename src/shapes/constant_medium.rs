@@ -0,0 +1,74 @@
+use crate::{
+    bvh::Aabb,
+    data::{Face, Hittable, Ray, RayHit},
+    interval::Interval,
+    materials::Material,
+    texture::Texture,
+};
+use rand::{Rng, RngCore};
+use std::sync::Arc;
+use vek::{Vec2, Vec3};
+
+/// A volume of constant density filling a convex boundary shape, like smoke or fog
+#[derive(Debug, Clone)]
+pub struct ConstantMedium {
+    pub boundary: Arc<dyn Hittable>,
+    pub negative_inverse_density: f32,
+    pub material: Material,
+}
+
+impl ConstantMedium {
+    /// `density` has to be a finite number above 0
+    pub fn new(boundary: Arc<dyn Hittable>, density: f32, albedo: Texture) -> Self {
+        Self {
+            boundary,
+            negative_inverse_density: -1. / density,
+            material: Material::Isotropic { albedo },
+        }
+    }
+}
+
+impl Hittable for ConstantMedium {
+    fn bounding_box(&self) -> Aabb {
+        self.boundary.bounding_box()
+    }
+
+    fn raycast(&self, ray: Ray, interval: Interval, rng: &mut dyn RngCore) -> Option<RayHit> {
+        let everywhere = Interval::new(f32::NEG_INFINITY, f32::INFINITY);
+
+        // Where the ray enters and leaves the boundary, also when starting inside it
+        let entry = self.boundary.raycast(ray, everywhere, rng)?;
+        let exit = self.boundary.raycast(
+            ray,
+            Interval::new(entry.distance + 0.0001, f32::INFINITY),
+            rng,
+        )?;
+
+        let entry_distance = entry.distance.max(interval.min).max(0.);
+        let exit_distance = exit.distance.min(interval.max);
+
+        if entry_distance >= exit_distance {
+            return None;
+        }
+
+        let ray_length = ray.direction.magnitude();
+        let distance_inside_boundary = (exit_distance - entry_distance) * ray_length;
+        let hit_distance = self.negative_inverse_density * f32::ln(1. - rng.gen::<f32>());
+
+        if hit_distance > distance_inside_boundary {
+            return None;
+        }
+
+        let distance = entry_distance + hit_distance / ray_length;
+
+        Some(RayHit {
+            distance,
+            point: ray.at(distance),
+            // Arbitrary, isotropic scattering doesn't use them
+            face: Face::Front,
+            normal: Vec3::unit_x(),
+            uv: Vec2::zero(),
+            material: self.material.clone(),
+        })
+    }
+}
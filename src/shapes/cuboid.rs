@@ -0,0 +1,121 @@
+use crate::{
+    bvh::Aabb,
+    data::{Hittable, Ray, RayHit},
+    interval::Interval,
+    materials::Material,
+    shapes::quad::Quad,
+};
+use rand::{Rng, RngCore};
+use vek::Vec3;
+
+/// An axis aligned box made of six quads, use an instance to rotate it
+#[derive(Debug, Clone)]
+pub struct Cuboid {
+    pub sides: [Quad; 6],
+
+    pub bounding_box: Aabb,
+}
+
+impl Cuboid {
+    /// Creates a box spanning from corner `a` to the opposite corner `b`
+    pub fn new(a: Vec3<f32>, b: Vec3<f32>, material: Material) -> Self {
+        let min = a.map2(b, f32::min);
+        let max = a.map2(b, f32::max);
+
+        let dx = Vec3::new(max.x - min.x, 0., 0.);
+        let dy = Vec3::new(0., max.y - min.y, 0.);
+        let dz = Vec3::new(0., 0., max.z - min.z);
+
+        let sides = [
+            Quad::new(Vec3::new(min.x, min.y, max.z), dx, dy, material.clone()), // front
+            Quad::new(Vec3::new(max.x, min.y, max.z), -dz, dy, material.clone()), // right
+            Quad::new(Vec3::new(max.x, min.y, min.z), -dx, dy, material.clone()), // back
+            Quad::new(Vec3::new(min.x, min.y, min.z), dz, dy, material.clone()), // left
+            Quad::new(Vec3::new(min.x, max.y, max.z), dx, -dz, material.clone()), // top
+            Quad::new(Vec3::new(min.x, min.y, min.z), dx, dz, material),         // bottom
+        ];
+
+        let bounding_box = Aabb::from_extremes(min, max).padded();
+
+        Self {
+            sides,
+            bounding_box,
+        }
+    }
+
+    /// Odds of sampling each side from `origin`, by area among the sides facing it, or all
+    /// of them from inside
+    fn side_weights(&self, origin: Vec3<f32>) -> [f32; 6] {
+        let facing = self.sides.each_ref().map(|side| {
+            if side.normal.dot(origin - side.origin) > 0. {
+                side.area()
+            } else {
+                0.
+            }
+        });
+
+        if facing.iter().sum::<f32>() > 0. {
+            facing
+        } else {
+            self.sides.each_ref().map(Quad::area)
+        }
+    }
+}
+
+impl Hittable for Cuboid {
+    fn bounding_box(&self) -> Aabb {
+        self.bounding_box
+    }
+
+    fn raycast(&self, ray: Ray, interval: Interval, rng: &mut dyn RngCore) -> Option<RayHit> {
+        if !self.bounding_box.ray_hits(ray, interval) {
+            return None;
+        }
+
+        let mut interval = interval;
+        let mut closest = None;
+
+        for side in &self.sides {
+            if let Some(ray_hit) = side.raycast(ray, interval, rng) {
+                interval.max = ray_hit.distance;
+                closest = Some(ray_hit);
+            }
+        }
+
+        closest
+    }
+
+    fn is_light(&self) -> bool {
+        self.sides[0].is_light()
+    }
+
+    fn pdf_value(&self, ray: Ray, rng: &mut dyn RngCore) -> f32 {
+        let weights = self.side_weights(ray.origin);
+        let total = weights.iter().sum::<f32>();
+
+        // A direction can pass through two sides
+        self.sides
+            .iter()
+            .zip(weights)
+            .filter(|(_, weight)| *weight > 0.)
+            .map(|(side, weight)| weight / total * side.pdf_value(ray, rng))
+            .sum()
+    }
+
+    fn sample_direction(&self, origin: Vec3<f32>, time: f32, rng: &mut dyn RngCore) -> Vec3<f32> {
+        let weights = self.side_weights(origin);
+        let mut target = rng.gen::<f32>() * weights.iter().sum::<f32>();
+
+        let side = self
+            .sides
+            .iter()
+            .zip(weights)
+            .find(|(_, weight)| {
+                target -= weight;
+                target < 0.
+            })
+            .map_or(&self.sides[5], |(side, _)| side);
+
+        side.sample_direction(origin, time, rng)
+    }
+}
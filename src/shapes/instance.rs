@@ -0,0 +1,117 @@
+use crate::{
+    bvh::Aabb,
+    data::{Hittable, Ray, RayHit},
+    interval::Interval,
+};
+use rand::RngCore;
+use std::sync::Arc;
+use vek::{Mat4, Vec3};
+
+/// Places an object, or a whole bvh, in the scene with an affine transform. The same object
+/// can be shared between many instances
+#[derive(Debug, Clone)]
+pub struct Instance {
+    pub object: Arc<dyn Hittable>,
+
+    /// From object space to world space
+    pub transform: Mat4<f32>,
+    pub inverse_transform: Mat4<f32>,
+
+    /// World space translation reached at time 1, starting from no offset at time 0
+    pub motion: Vec3<f32>,
+
+    pub bounding_box: Aabb,
+}
+
+impl Instance {
+    pub fn new(object: Arc<dyn Hittable>, transform: Mat4<f32>) -> Self {
+        Self::moving(object, transform, Vec3::zero())
+    }
+
+    pub fn moving(object: Arc<dyn Hittable>, transform: Mat4<f32>, motion: Vec3<f32>) -> Self {
+        let inverse_transform = transform.inverted();
+
+        let bounding_box = object
+            .bounding_box()
+            .corners()
+            .map(|corner| {
+                let corner = transform.mul_point(corner);
+                Aabb::from_extremes(corner, corner)
+            })
+            .into_iter()
+            .collect::<Option<Aabb>>()
+            .unwrap();
+
+        // Covers the whole motion
+        let bounding_box = Aabb::combine(bounding_box, bounding_box.translated(motion)).padded();
+
+        Self {
+            object,
+            transform,
+            inverse_transform,
+            motion,
+            bounding_box,
+        }
+    }
+
+    /// `ray` in object space. The direction isn't normalized, so distances are the same in
+    /// both spaces
+    fn object_ray(&self, ray: Ray) -> Ray {
+        let offset = self.motion * ray.time;
+
+        Ray::new(
+            self.inverse_transform.mul_point(ray.origin - offset),
+            self.inverse_transform.mul_direction(ray.direction),
+            ray.time,
+        )
+    }
+}
+
+impl Hittable for Instance {
+    fn bounding_box(&self) -> Aabb {
+        self.bounding_box
+    }
+
+    fn raycast(&self, ray: Ray, interval: Interval, rng: &mut dyn RngCore) -> Option<RayHit> {
+        let ray_hit = self.object.raycast(self.object_ray(ray), interval, rng)?;
+
+        // Normals transform with the inverse transpose to stay perpendicular to the surface
+        let normal = self
+            .inverse_transform
+            .transposed()
+            .mul_direction(ray_hit.normal)
+            .normalized();
+
+        Some(RayHit {
+            point: self.transform.mul_point(ray_hit.point) + self.motion * ray.time,
+            normal,
+            ..ray_hit
+        })
+    }
+
+    fn is_light(&self) -> bool {
+        self.object.is_light()
+    }
+
+    fn pdf_value(&self, ray: Ray, rng: &mut dyn RngCore) -> f32 {
+        let object_ray =
+            self.object_ray(Ray::new(ray.origin, ray.direction.normalized(), ray.time));
+
+        // Scaling and shearing change solid angles, this is the Jacobian from world space
+        // directions to object space ones
+        let jacobian =
+            self.inverse_transform.determinant().abs() / object_ray.direction.magnitude().powi(3);
+
+        self.object.pdf_value(object_ray, rng) * jacobian
+    }
+
+    fn sample_direction(&self, origin: Vec3<f32>, time: f32, rng: &mut dyn RngCore) -> Vec3<f32> {
+        let object_origin = self
+            .inverse_transform
+            .mul_point(origin - self.motion * time);
+
+        let direction = self.object.sample_direction(object_origin, time, rng);
+
+        self.transform.mul_direction(direction)
+    }
+}
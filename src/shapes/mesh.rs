@@ -0,0 +1,149 @@
+use crate::{
+    bvh::{Aabb, BvhNode},
+    data::{Hittable, Ray, RayHit},
+    interval::Interval,
+    linear_bvh::LinearBvh,
+    materials::Material,
+    shapes::triangle::{MeshData, Triangle},
+};
+use rand::{Rng, RngCore};
+use std::sync::Arc;
+use vek::{Vec2, Vec3};
+
+/// Triangles sharing the same vertex buffers and material, with their own bvh
+#[derive(Debug, Clone)]
+pub struct Mesh {
+    pub data: Arc<MeshData>,
+    pub bvh: Arc<LinearBvh<Triangle>>,
+
+    /// Running total of the triangle areas, in the order of `bvh.objects()`
+    pub cumulative_areas: Arc<Vec<f32>>,
+}
+
+impl Mesh {
+    /// None if there are no triangles
+    pub fn new(
+        positions: Vec<Vec3<f32>>,
+        indices: &[[u32; 3]],
+        normals: Option<Vec<Vec3<f32>>>,
+        uvs: Option<Vec<Vec2<f32>>>,
+        material: Material,
+    ) -> Option<Self> {
+        let data = Arc::new(MeshData {
+            positions,
+            normals,
+            uvs,
+            material,
+        });
+
+        Self::from_mesh_data(data, indices)
+    }
+
+    /// None if there are no triangles
+    pub fn from_mesh_data(data: Arc<MeshData>, indices: &[[u32; 3]]) -> Option<Self> {
+        let triangles = indices
+            .iter()
+            .map(|&indices| Triangle::from_mesh_data(data.clone(), indices))
+            .collect::<Vec<_>>();
+
+        let bvh = LinearBvh::from(BvhNode::new_sah(&triangles)?);
+
+        let mut total = 0.;
+        let cumulative_areas = bvh
+            .objects()
+            .iter()
+            .map(|triangle| {
+                total += triangle.area();
+                total
+            })
+            .collect();
+
+        Some(Self {
+            data,
+            bvh: Arc::new(bvh),
+            cumulative_areas: Arc::new(cumulative_areas),
+        })
+    }
+
+    pub fn area(&self) -> f32 {
+        self.cumulative_areas.last().copied().unwrap_or(0.)
+    }
+}
+
+impl Hittable for Mesh {
+    fn bounding_box(&self) -> Aabb {
+        self.bvh.bounding_box()
+    }
+
+    fn raycast(&self, ray: Ray, interval: Interval, rng: &mut dyn RngCore) -> Option<RayHit> {
+        self.bvh.raycast(ray, interval, rng)
+    }
+
+    fn is_light(&self) -> bool {
+        self.data.material.is_emissive()
+    }
+
+    /// Triangles are picked by area, so every one the ray passes through counts
+    fn pdf_value(&self, ray: Ray, rng: &mut dyn RngCore) -> f32 {
+        // Only degenerate triangles, which can't be hit
+        if self.area() <= 0. {
+            return 0.;
+        }
+
+        let mut pdf = 0.;
+
+        self.bvh
+            .for_each_candidate(ray, Interval::new(0.001, f32::INFINITY), |triangle| {
+                pdf += triangle.pdf_value(ray, rng) * triangle.area()
+            });
+
+        pdf / self.area()
+    }
+
+    fn sample_direction(&self, origin: Vec3<f32>, time: f32, rng: &mut dyn RngCore) -> Vec3<f32> {
+        let target = rng.gen::<f32>() * self.area();
+        let index = self
+            .cumulative_areas
+            .partition_point(|&sum| sum <= target)
+            .min(self.cumulative_areas.len() - 1);
+
+        self.bvh.objects()[index].sample_direction(origin, time, rng)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::texture::Texture;
+    use rand::{rngs::SmallRng, SeedableRng};
+    use vek::Rgb;
+
+    fn light() -> Material {
+        Material::DiffuseLight {
+            strength: Texture::solid(Rgb::one()),
+            two_sided: false,
+            cosine_power: 0.,
+            cone: None,
+        }
+    }
+
+    #[test]
+    fn meshes_need_triangles() {
+        let positions = vec![Vec3::zero(), Vec3::unit_x(), Vec3::unit_y()];
+
+        assert!(Mesh::new(positions, &[], None, None, light()).is_none());
+    }
+
+    #[test]
+    fn degenerate_meshes_have_no_pdf() {
+        // All three vertices on a line
+        let positions = vec![Vec3::zero(), Vec3::unit_x(), Vec3::unit_x() * 2.];
+        let mesh = Mesh::new(positions, &[[0, 1, 2]], None, None, light()).unwrap();
+
+        let ray = Ray::new(Vec3::new(1., 0., 1.), -Vec3::unit_z(), 0.);
+        let pdf = mesh.pdf_value(ray, &mut SmallRng::seed_from_u64(0));
+
+        assert_eq!(mesh.area(), 0.);
+        assert_eq!(pdf, 0.);
+    }
+}
@@ -1,2 +1,61 @@
+pub mod constant_medium;
+pub mod cuboid;
+pub mod instance;
+pub mod mesh;
 pub mod quad;
 pub mod sphere;
+pub mod triangle;
+
+#[cfg(test)]
+mod tests {
+    use crate::data::{Hittable, Ray};
+    use crate::materials::Material;
+    use crate::shapes::{
+        cuboid::Cuboid, instance::Instance, mesh::Mesh, quad::Quad, sphere::Sphere,
+        triangle::Triangle,
+    };
+    use crate::texture::Texture;
+    use rand::{rngs::SmallRng, SeedableRng};
+    use std::sync::Arc;
+    use vek::{Mat4, Rgb, Vec3};
+
+    #[test]
+    fn lights_sample_directions_they_have_a_pdf_for() {
+        let light = || Material::DiffuseLight {
+            strength: Texture::solid(Rgb::one()),
+            two_sided: true,
+            cosine_power: 0.,
+            cone: None,
+        };
+
+        let (a, b, c) = (Vec3::zero(), Vec3::unit_x(), Vec3::unit_y());
+        let transform = Mat4::<f32>::translation_3d(Vec3::unit_z()) * Mat4::rotation_y(0.5);
+
+        let shapes: [Arc<dyn Hittable>; 7] = [
+            Arc::new(Sphere::new(a, 1., light())),
+            Arc::new(Quad::new(a, b, c, light())),
+            Arc::new(Quad::disk(a, Vec3::unit_z(), 1., light())),
+            Arc::new(Triangle::new(a, b, c, light())),
+            Arc::new(Cuboid::new(a, Vec3::one(), light())),
+            Arc::new(Mesh::new(vec![a, b, c], &[[0, 1, 2]], None, None, light()).unwrap()),
+            Arc::new(Instance::new(
+                Arc::new(Cuboid::new(a, Vec3::one(), light())),
+                transform,
+            )),
+        ];
+
+        let origin = Vec3::new(0.3, 0.4, 5.);
+        let rng = &mut SmallRng::seed_from_u64(0);
+
+        for shape in shapes {
+            assert!(shape.is_light(), "{shape:?}");
+
+            for _ in 0..64 {
+                let direction = shape.sample_direction(origin, 0., rng);
+                let pdf = shape.pdf_value(Ray::new(origin, direction, 0.), rng);
+
+                assert!(pdf > 0. && pdf.is_finite(), "{shape:?}: {pdf}");
+            }
+        }
+    }
+}
@@ -1,17 +1,33 @@
+use rand::{Rng, RngCore};
+use std::f32::consts::PI;
 use vek::{Vec2, Vec3};
 
 use crate::{
     bvh::Aabb,
-    data::{Face, Hittable, Ray, RayHit},
+    data::{Face, Hittable, Onb, Ray, RayHit},
     interval::Interval,
     materials::Material,
 };
 
+/// Which part of the plane spanned by `u` and `v` a [`Quad`] covers
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QuadShape {
+    /// `origin` to `origin + u + v`
+    Parallelogram,
+
+    /// Corners at `origin`, `origin + u` and `origin + v`
+    Triangle,
+
+    /// Centered on `origin`, with `u` and `v` as the semi-axes
+    Ellipse,
+}
+
 #[derive(Debug, Clone)]
 pub struct Quad {
     pub origin: Vec3<f32>,
     pub u: Vec3<f32>,
     pub v: Vec3<f32>,
+    pub shape: QuadShape,
 
     pub normal: Vec3<f32>,
     pub w: Vec3<f32>,
@@ -23,18 +39,61 @@ pub struct Quad {
 
 impl Quad {
     pub fn new(origin: Vec3<f32>, u: Vec3<f32>, v: Vec3<f32>, material: Material) -> Quad {
+        Self::with_shape(origin, u, v, QuadShape::Parallelogram, material)
+    }
+
+    pub fn triangle(origin: Vec3<f32>, u: Vec3<f32>, v: Vec3<f32>, material: Material) -> Quad {
+        Self::with_shape(origin, u, v, QuadShape::Triangle, material)
+    }
+
+    pub fn ellipse(center: Vec3<f32>, u: Vec3<f32>, v: Vec3<f32>, material: Material) -> Quad {
+        Self::with_shape(center, u, v, QuadShape::Ellipse, material)
+    }
+
+    pub fn disk(center: Vec3<f32>, normal: Vec3<f32>, radius: f32, material: Material) -> Quad {
+        let Onb { u, v, .. } = Onb::new(normal);
+
+        Self::ellipse(center, u * radius, v * radius, material)
+    }
+
+    pub fn with_shape(
+        origin: Vec3<f32>,
+        u: Vec3<f32>,
+        v: Vec3<f32>,
+        shape: QuadShape,
+        material: Material,
+    ) -> Quad {
         let n = u.cross(v);
         let w = n / n.dot(n);
 
         let normal = n.normalized();
         let distance = normal.dot(origin);
 
-        let bounding_box = Aabb::from_extremes(origin, origin + u + v).padded();
+        let bounding_box = match shape {
+            QuadShape::Parallelogram => [origin, origin + u, origin + v, origin + u + v]
+                .map(|corner| Aabb::from_extremes(corner, corner))
+                .into_iter()
+                .collect::<Option<Aabb>>(),
+
+            QuadShape::Triangle => [origin, origin + u, origin + v]
+                .map(|corner| Aabb::from_extremes(corner, corner))
+                .into_iter()
+                .collect::<Option<Aabb>>(),
+
+            QuadShape::Ellipse => {
+                let extent = u.map2(v, |u, v| f32::sqrt(u * u + v * v));
+
+                Some(Aabb::from_extremes(origin - extent, origin + extent))
+            }
+        };
+
+        let bounding_box = bounding_box.unwrap().padded();
 
         Self {
             origin,
             u,
             v,
+            shape,
 
             normal,
             w,
@@ -44,12 +103,59 @@ impl Quad {
             material,
         }
     }
-}
 
-fn is_interior(alpha: f32, beta: f32) -> bool {
-    let is_outerior = (alpha < 0.) || (1. < alpha) || (beta < 0.) || (1. < beta);
+    pub fn area(&self) -> f32 {
+        let parallelogram_area = self.u.cross(self.v).magnitude();
+
+        match self.shape {
+            QuadShape::Parallelogram => parallelogram_area,
+            QuadShape::Triangle => parallelogram_area / 2.,
+            QuadShape::Ellipse => parallelogram_area * PI,
+        }
+    }
+
+    /// Uniformly distributed random point on the shape
+    pub fn random_point(&self, rng: &mut dyn RngCore) -> Vec3<f32> {
+        let alpha = rng.gen::<f32>();
+        let beta = rng.gen::<f32>();
+
+        let (alpha, beta) = match self.shape {
+            QuadShape::Parallelogram => (alpha, beta),
 
-    !is_outerior
+            // Fold the half of the parallelogram outside the triangle back into it
+            QuadShape::Triangle if alpha + beta > 1. => (1. - alpha, 1. - beta),
+            QuadShape::Triangle => (alpha, beta),
+
+            QuadShape::Ellipse => {
+                let radius = alpha.sqrt();
+                let angle = 2. * PI * beta;
+
+                (radius * angle.cos(), radius * angle.sin())
+            }
+        };
+
+        self.origin + self.u * alpha + self.v * beta
+    }
+
+    /// Texture coordinate of a point with plane coordinates `alpha` and `beta`, if it's
+    /// inside the shape
+    fn uv(&self, alpha: f32, beta: f32) -> Option<Vec2<f32>> {
+        let unit_interval = 0. ..=1.;
+
+        match self.shape {
+            QuadShape::Parallelogram => (unit_interval.contains(&alpha)
+                && unit_interval.contains(&beta))
+            .then_some(Vec2::new(alpha, beta)),
+
+            QuadShape::Triangle => {
+                (alpha >= 0. && beta >= 0. && alpha + beta <= 1.).then_some(Vec2::new(alpha, beta))
+            }
+
+            QuadShape::Ellipse => {
+                (alpha * alpha + beta * beta <= 1.).then_some(Vec2::new(alpha, beta) / 2. + 0.5)
+            }
+        }
+    }
 }
 
 impl Hittable for Quad {
@@ -57,7 +163,7 @@ impl Hittable for Quad {
         self.bounding_box
     }
 
-    fn raycast(&self, ray: Ray, interval: Interval) -> Option<RayHit> {
+    fn raycast(&self, ray: Ray, interval: Interval, _rng: &mut dyn RngCore) -> Option<RayHit> {
         let outward_normal = self.normal;
         let denominator = self.normal.dot(ray.direction);
 
@@ -76,11 +182,7 @@ impl Hittable for Quad {
         let alpha = self.w.dot(planat_hit_vector.cross(self.v));
         let beta = self.w.dot(self.u.cross(planat_hit_vector));
 
-        if !is_interior(alpha, beta) {
-            return None;
-        }
-
-        let uv = Vec2::new(alpha, beta);
+        let uv = self.uv(alpha, beta)?;
 
         let face = ray.get_face(outward_normal);
 
@@ -100,4 +202,23 @@ impl Hittable for Quad {
             material,
         })
     }
+
+    fn is_light(&self) -> bool {
+        self.material.is_emissive()
+    }
+
+    fn pdf_value(&self, ray: Ray, rng: &mut dyn RngCore) -> f32 {
+        let Some(ray_hit) = self.raycast(ray, Interval::new(0.001, f32::INFINITY), rng) else {
+            return 0.;
+        };
+
+        let distance_squared = ray_hit.distance.powi(2) * ray.direction.magnitude_squared();
+        let cosine = ray.direction.dot(self.normal).abs() / ray.direction.magnitude();
+
+        distance_squared / (cosine * self.area())
+    }
+
+    fn sample_direction(&self, origin: Vec3<f32>, _time: f32, rng: &mut dyn RngCore) -> Vec3<f32> {
+        self.random_point(rng) - origin
+    }
 }
@@ -1,16 +1,21 @@
 use crate::data::Ray;
+use crate::extensions::RngExtension;
 use crate::{
     bvh::Aabb,
-    data::{Face, Hittable, RayHit},
+    data::{Face, Hittable, Onb, RayHit},
     interval::Interval,
     materials::Material,
 };
+use rand::{Rng, RngCore};
 use std::f32::consts::PI;
 use vek::{Vec2, Vec3};
 
 #[derive(Debug, Clone)]
 pub struct Sphere {
+    /// Center at time 0
     pub center: Vec3<f32>,
+    /// How far the center moves between time 0 and time 1
+    pub motion: Vec3<f32>,
     pub radius: f32,
 
     pub bounding_box: Aabb,
@@ -19,11 +24,20 @@ pub struct Sphere {
 
 impl Sphere {
     pub fn new(center: Vec3<f32>, radius: f32, material: Material) -> Self {
+        Self::moving(center, center, radius, material)
+    }
+
+    /// A sphere moving linearly from `start` at time 0 to `end` at time 1
+    pub fn moving(start: Vec3<f32>, end: Vec3<f32>, radius: f32, material: Material) -> Self {
         let size = Vec3::broadcast(radius);
-        let bounding_box = Aabb::from_extremes(center - size, center + size);
+        let bounding_box = Aabb::combine(
+            Aabb::from_extremes(start - size, start + size),
+            Aabb::from_extremes(end - size, end + size),
+        );
 
         Self {
-            center,
+            center: start,
+            motion: end - start,
             radius,
             bounding_box,
             material,
@@ -46,8 +60,9 @@ impl Hittable for Sphere {
         self.bounding_box
     }
 
-    fn raycast(&self, ray: Ray, interval: Interval) -> Option<RayHit> {
-        let center_to_origin = ray.origin - self.center;
+    fn raycast(&self, ray: Ray, interval: Interval, _rng: &mut dyn RngCore) -> Option<RayHit> {
+        let center = self.center + self.motion * ray.time;
+        let center_to_origin = ray.origin - center;
         let a = ray.direction.magnitude_squared();
         let half_b = Vec3::dot(center_to_origin, ray.direction);
         let c = center_to_origin.magnitude_squared() - self.radius.powi(2);
@@ -72,7 +87,7 @@ impl Hittable for Sphere {
         let distance = root;
         let point = ray.at(distance);
 
-        let outward_normal = (point - self.center) / self.radius;
+        let outward_normal = (point - center) / self.radius;
         let face = ray.get_face(outward_normal);
 
         let normal = match face {
@@ -93,4 +108,50 @@ impl Hittable for Sphere {
             material,
         })
     }
+
+    fn is_light(&self) -> bool {
+        self.material.is_emissive()
+    }
+
+    fn pdf_value(&self, ray: Ray, rng: &mut dyn RngCore) -> f32 {
+        if self
+            .raycast(ray, Interval::new(0.001, f32::INFINITY), rng)
+            .is_none()
+        {
+            return 0.;
+        }
+
+        let center = self.center + self.motion * ray.time;
+        let distance_squared = (center - ray.origin).magnitude_squared();
+
+        // From inside every direction hits, and they're sampled uniformly
+        if distance_squared <= self.radius.powi(2) {
+            return 1. / (4. * PI);
+        }
+
+        let cos_theta_max = f32::sqrt(1. - self.radius.powi(2) / distance_squared);
+        let solid_angle = 2. * PI * (1. - cos_theta_max);
+
+        1. / solid_angle
+    }
+
+    fn sample_direction(&self, origin: Vec3<f32>, time: f32, rng: &mut dyn RngCore) -> Vec3<f32> {
+        let center = self.center + self.motion * time;
+        let direction = center - origin;
+        let distance_squared = direction.magnitude_squared();
+
+        if distance_squared <= self.radius.powi(2) {
+            return rng.random_unit_vector();
+        }
+
+        // Uniform over the cone of directions that hit the sphere
+        let cos_theta_max = f32::sqrt(1. - self.radius.powi(2) / distance_squared);
+        let cos_theta = 1. + rng.gen::<f32>() * (cos_theta_max - 1.);
+        let sin_theta = f32::sqrt(1. - cos_theta * cos_theta);
+        let phi = 2. * PI * rng.gen::<f32>();
+
+        let local = Vec3::new(phi.cos() * sin_theta, phi.sin() * sin_theta, cos_theta);
+
+        Onb::new(direction).to_world(local)
+    }
 }
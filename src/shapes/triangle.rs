@@ -0,0 +1,239 @@
+use crate::{
+    bvh::Aabb,
+    data::{Face, Hittable, Ray, RayHit},
+    interval::Interval,
+    materials::Material,
+};
+use rand::{Rng, RngCore};
+use std::ops::{Add, Mul};
+use std::sync::Arc;
+use vek::{Vec2, Vec3};
+
+/// Vertex buffers shared between the triangles of a mesh
+#[derive(Debug)]
+pub struct MeshData {
+    pub positions: Vec<Vec3<f32>>,
+
+    /// Per vertex normals used for smooth shading, indexed like `positions`
+    pub normals: Option<Vec<Vec3<f32>>>,
+
+    /// Per vertex texture coordinates, indexed like `positions`
+    pub uvs: Option<Vec<Vec2<f32>>>,
+
+    pub material: Material,
+}
+
+#[derive(Debug, Clone)]
+pub struct Triangle {
+    pub data: Arc<MeshData>,
+    pub indices: [u32; 3],
+
+    pub bounding_box: Aabb,
+}
+
+impl Triangle {
+    pub fn new(a: Vec3<f32>, b: Vec3<f32>, c: Vec3<f32>, material: Material) -> Self {
+        let data = MeshData {
+            positions: vec![a, b, c],
+            normals: None,
+            uvs: None,
+            material,
+        };
+
+        Self::from_mesh_data(Arc::new(data), [0, 1, 2])
+    }
+
+    pub fn from_mesh_data(data: Arc<MeshData>, indices: [u32; 3]) -> Self {
+        let [a, b, c] = indices.map(|index| data.positions[index as usize]);
+
+        let min = a.map2(b, f32::min).map2(c, f32::min);
+        let max = a.map2(b, f32::max).map2(c, f32::max);
+        let bounding_box = Aabb::from_extremes(min, max).padded();
+
+        Self {
+            data,
+            indices,
+            bounding_box,
+        }
+    }
+
+    pub fn area(&self) -> f32 {
+        let [a, b, c] = self.vertices();
+
+        (b - a).cross(c - a).magnitude() / 2.
+    }
+
+    /// Uniformly distributed random point on the triangle
+    pub fn random_point(&self, rng: &mut dyn RngCore) -> Vec3<f32> {
+        let [a, b, c] = self.vertices();
+
+        let s = rng.gen::<f32>().sqrt();
+        let t = rng.gen::<f32>();
+
+        a * (1. - s) + b * (s * (1. - t)) + c * (s * t)
+    }
+
+    fn vertices(&self) -> [Vec3<f32>; 3] {
+        self.indices
+            .map(|index| self.data.positions[index as usize])
+    }
+
+    /// Interpolates a per vertex attribute with barycentric coordinates
+    fn interpolate<T>(&self, values: &[T], barycentric: Vec3<f32>) -> T
+    where
+        T: Copy + Add<Output = T> + Mul<f32, Output = T>,
+    {
+        let [a, b, c] = self.indices.map(|index| values[index as usize]);
+
+        a * barycentric.x + b * barycentric.y + c * barycentric.z
+    }
+}
+
+impl Hittable for Triangle {
+    fn bounding_box(&self) -> Aabb {
+        self.bounding_box
+    }
+
+    fn raycast(&self, ray: Ray, interval: Interval, _rng: &mut dyn RngCore) -> Option<RayHit> {
+        // Möller–Trumbore
+        let [a, b, c] = self.vertices();
+
+        let edge_1 = b - a;
+        let edge_2 = c - a;
+
+        let p = ray.direction.cross(edge_2);
+        let determinant = edge_1.dot(p);
+
+        // The determinant is the cosine between the ray and the normal scaled by the ray's
+        // length and twice the area, so small triangles aren't mistaken for parallel ones
+        let scale = ray.direction.magnitude() * edge_1.cross(edge_2).magnitude();
+        if determinant.abs() <= 1e-6 * scale {
+            return None;
+        }
+
+        let inverse_determinant = 1. / determinant;
+
+        let t = ray.origin - a;
+        let beta = t.dot(p) * inverse_determinant;
+        if !(0. ..=1.).contains(&beta) {
+            return None;
+        }
+
+        let q = t.cross(edge_1);
+        let gamma = ray.direction.dot(q) * inverse_determinant;
+        if gamma < 0. || beta + gamma > 1. {
+            return None;
+        }
+
+        let distance = edge_2.dot(q) * inverse_determinant;
+        if !interval.contains(distance) {
+            return None;
+        }
+
+        let alpha = 1. - beta - gamma;
+        let point = ray.at(distance);
+
+        let barycentric = Vec3::new(alpha, beta, gamma);
+
+        let outward_normal = match &self.data.normals {
+            Some(normals) => self.interpolate(normals, barycentric).normalized(),
+            None => edge_1.cross(edge_2).normalized(),
+        };
+
+        // The geometric normal decides the face, so smooth normals can't flip it
+        let face = ray.get_face(edge_1.cross(edge_2));
+
+        let normal = match face {
+            Face::Front => outward_normal,
+            Face::Back => -outward_normal,
+        };
+
+        let uv = match &self.data.uvs {
+            Some(uvs) => self.interpolate(uvs, barycentric),
+            None => Vec2::new(beta, gamma),
+        };
+
+        let material = self.data.material.clone();
+
+        Some(RayHit {
+            distance,
+            point,
+            face,
+            normal,
+            uv,
+            material,
+        })
+    }
+
+    fn is_light(&self) -> bool {
+        self.data.material.is_emissive()
+    }
+
+    fn pdf_value(&self, ray: Ray, rng: &mut dyn RngCore) -> f32 {
+        let Some(ray_hit) = self.raycast(ray, Interval::new(0.001, f32::INFINITY), rng) else {
+            return 0.;
+        };
+
+        // The geometric normal, smooth normals don't change the area
+        let [a, b, c] = self.vertices();
+        let normal = (b - a).cross(c - a).normalized();
+
+        let distance_squared = ray_hit.distance.powi(2) * ray.direction.magnitude_squared();
+        let cosine = ray.direction.dot(normal).abs() / ray.direction.magnitude();
+
+        distance_squared / (cosine * self.area())
+    }
+
+    fn sample_direction(&self, origin: Vec3<f32>, _time: f32, rng: &mut dyn RngCore) -> Vec3<f32> {
+        self.random_point(rng) - origin
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::texture::Texture;
+    use rand::{rngs::SmallRng, SeedableRng};
+    use vek::Rgb;
+
+    fn triangle(size: f32) -> Triangle {
+        let material = Material::Diffuse {
+            albedo: Texture::solid(Rgb::broadcast(0.5)),
+        };
+
+        Triangle::new(
+            Vec3::zero(),
+            Vec3::unit_x() * size,
+            Vec3::unit_y() * size,
+            material,
+        )
+    }
+
+    #[test]
+    fn small_triangles_are_hit() {
+        let rng = &mut SmallRng::seed_from_u64(0);
+
+        for size in [1e-5, 1e-3, 1., 1e3] {
+            let ray = Ray::new(Vec3::new(0.25, 0.25, 1.) * size, -Vec3::unit_z(), 0.);
+            let ray_hit = triangle(size).raycast(ray, Interval::new(0., f32::INFINITY), rng);
+
+            let distance = ray_hit.map(|ray_hit| ray_hit.distance);
+            assert!(
+                distance.is_some_and(|distance| (distance / size - 1.).abs() < 1e-4),
+                "{size}: {distance:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn parallel_rays_miss() {
+        let ray = Ray::new(Vec3::new(-1., 0.25, 0.), Vec3::unit_x(), 0.);
+        let ray_hit = triangle(1.).raycast(
+            ray,
+            Interval::new(0., f32::INFINITY),
+            &mut SmallRng::seed_from_u64(0),
+        );
+
+        assert!(ray_hit.is_none());
+    }
+}
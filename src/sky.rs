@@ -0,0 +1,211 @@
+use crate::data::Onb;
+use crate::extensions::RngExtension;
+use rand::Rng;
+use std::f32::consts::PI;
+use vek::{Rgb, Vec3};
+
+/// Brings the model's luminance, in kcd/m², to about 0.5 at the zenith of a clear day
+const LUMINANCE_SCALE: f32 = 0.05;
+
+/// Irradiance of the sun at the zenith before going through the atmosphere, relative to
+/// the sky's radiance
+const SUN_IRRADIANCE: f32 = 8.;
+
+/// Odds of sampling the sun disk instead of the whole sky
+const SUN_SAMPLING_PROBABILITY: f32 = 0.5;
+
+/// Preetham et al. daylight model, with a disk for the sun. y is up, and the sky is
+/// extended below the horizon with the color at the horizon
+#[derive(Debug, Clone)]
+pub struct Sky {
+    /// Unit length, towards the sun
+    sun_direction: Vec3<f32>,
+
+    /// Cosine of the sun's angular radius
+    sun_cos_radius: f32,
+    sun_solid_angle: f32,
+    sun_radiance: Rgb<f32>,
+
+    strength: f32,
+
+    /// Perez distribution coefficients for Y, x and y
+    coefficients: [[f32; 5]; 3],
+
+    /// Sky color at the zenith, divided by the Perez distribution there, in Yxy
+    zenith: [f32; 3],
+}
+
+impl Sky {
+    /// `turbidity` is haziness, from 2 for a clear day to around 10. `sun_size` is the
+    /// angular diameter of the sun in radians, 0.0095 for the real one. A sun too small for
+    /// its cosine to fall below 1 in f32 would have no solid angle and infinite radiance
+    pub fn new(sun_direction: Vec3<f32>, turbidity: f32, sun_size: f32, strength: f32) -> Self {
+        let sun_direction = sun_direction.normalized();
+        let t = turbidity.max(1.);
+
+        let coefficients = [
+            [
+                0.1787 * t - 1.4630,
+                -0.3554 * t + 0.4275,
+                -0.0227 * t + 5.3251,
+                0.1206 * t - 2.5771,
+                -0.0670 * t + 0.3703,
+            ],
+            [
+                -0.0193 * t - 0.2592,
+                -0.0665 * t + 0.0008,
+                -0.0004 * t + 0.2125,
+                -0.0641 * t - 0.8989,
+                -0.0033 * t + 0.0452,
+            ],
+            [
+                -0.0167 * t - 0.2608,
+                -0.0950 * t + 0.0092,
+                -0.0079 * t + 0.2102,
+                -0.0441 * t - 1.6537,
+                -0.0109 * t + 0.0529,
+            ],
+        ];
+
+        // Angle of the sun to the zenith, the model stops at the horizon
+        let theta = sun_direction.y.clamp(0., 1.).acos();
+        let theta_2 = theta * theta;
+        let theta_3 = theta_2 * theta;
+
+        let chi = (4. / 9. - t / 120.) * (PI - 2. * theta);
+        let luminance = (4.0453 * t - 4.9710) * chi.tan() - 0.2155 * t + 2.4192;
+
+        let polynomial = |[a, b, c]: [[f32; 4]; 3]| {
+            let row = |[w, x, y, z]: [f32; 4]| w * theta_3 + x * theta_2 + y * theta + z;
+            t * t * row(a) + t * row(b) + row(c)
+        };
+
+        let x = polynomial([
+            [0.00166, -0.00375, 0.00209, 0.],
+            [-0.02903, 0.06377, -0.03202, 0.00394],
+            [0.11693, -0.21196, 0.06052, 0.25886],
+        ]);
+        let y = polynomial([
+            [0.00275, -0.00610, 0.00317, 0.],
+            [-0.04214, 0.08970, -0.04153, 0.00516],
+            [0.15346, -0.26756, 0.06670, 0.26688],
+        ]);
+
+        let mut zenith = [luminance.max(0.), x, y];
+
+        for (value, coefficients) in zenith.iter_mut().zip(coefficients) {
+            *value /= perez(coefficients, 1., theta.cos());
+        }
+
+        let sun_cos_radius = f32::cos(sun_size / 2.);
+        let sun_solid_angle = 2. * PI * (1. - sun_cos_radius);
+
+        // Hidden by the ground once it has set
+        let sun_radiance = if sun_direction.y < 0. {
+            Rgb::zero()
+        } else {
+            sun_transmittance(theta, t) * (SUN_IRRADIANCE / sun_solid_angle)
+        };
+
+        Self {
+            sun_direction,
+            sun_cos_radius,
+            sun_solid_angle,
+            sun_radiance,
+            strength,
+            coefficients,
+            zenith,
+        }
+    }
+
+    fn sky_radiance(&self, direction: Vec3<f32>) -> Rgb<f32> {
+        let cos_theta = direction.y.max(0.001);
+        let cos_gamma = direction.dot(self.sun_direction).clamp(-1., 1.);
+
+        let [luminance, x, y] = [0, 1, 2].map(|index| {
+            self.zenith[index] * perez(self.coefficients[index], cos_theta, cos_gamma)
+        });
+
+        xyy_to_rgb(luminance * LUMINANCE_SCALE, x, y)
+    }
+
+    /// Light arriving from `direction`, which must be unit length
+    pub fn radiance(&self, direction: Vec3<f32>) -> Rgb<f32> {
+        let mut radiance = self.sky_radiance(direction);
+
+        if direction.dot(self.sun_direction) >= self.sun_cos_radius {
+            radiance += self.sun_radiance;
+        }
+
+        radiance * self.strength
+    }
+
+    /// Picks the sun disk or any direction, since the sun is too small and bright to be
+    /// found by chance
+    pub fn sample_direction(&self, rng: &mut impl Rng) -> Vec3<f32> {
+        if rng.gen::<f32>() >= SUN_SAMPLING_PROBABILITY {
+            return rng.random_unit_vector();
+        }
+
+        let cos_theta = 1. + rng.gen::<f32>() * (self.sun_cos_radius - 1.);
+        let sin_theta = f32::sqrt(1. - cos_theta * cos_theta);
+        let phi = 2. * PI * rng.gen::<f32>();
+
+        let local = Vec3::new(phi.cos() * sin_theta, phi.sin() * sin_theta, cos_theta);
+
+        Onb::new(self.sun_direction).to_world(local)
+    }
+
+    pub fn pdf(&self, direction: Vec3<f32>) -> f32 {
+        let mut pdf = (1. - SUN_SAMPLING_PROBABILITY) / (4. * PI);
+
+        if direction.dot(self.sun_direction) >= self.sun_cos_radius {
+            pdf += SUN_SAMPLING_PROBABILITY / self.sun_solid_angle;
+        }
+
+        pdf
+    }
+}
+
+/// Relative brightness of the sky `theta` from the zenith and `gamma` from the sun
+fn perez([a, b, c, d, e]: [f32; 5], cos_theta: f32, cos_gamma: f32) -> f32 {
+    let gamma = cos_gamma.acos();
+
+    (1. + a * f32::exp(b / cos_theta)) * (1. + c * f32::exp(d * gamma) + e * cos_gamma * cos_gamma)
+}
+
+/// Rayleigh and aerosol scattering of sunlight on its way through the atmosphere, at
+/// wavelengths around the middle of each channel
+fn sun_transmittance(theta: f32, turbidity: f32) -> Rgb<f32> {
+    let wavelengths = Rgb::new(0.65, 0.55, 0.45_f32);
+
+    // Kasten's relative air mass, which stays finite at the horizon
+    let air_mass = 1. / (theta.cos() + 0.15 * f32::powf(93.885 - theta.to_degrees(), -1.253));
+
+    // Ångström's turbidity formula for aerosols
+    let beta = 0.04608 * turbidity - 0.04586;
+
+    wavelengths.map(|wavelength| {
+        let rayleigh = 0.008735 * wavelength.powf(-4.08);
+        let aerosol = beta * wavelength.powf(-1.3);
+
+        f32::exp(-(rayleigh + aerosol) * air_mass)
+    })
+}
+
+/// To linear sRGB
+fn xyy_to_rgb(luminance: f32, x: f32, y: f32) -> Rgb<f32> {
+    if y <= 0. {
+        return Rgb::zero();
+    }
+
+    let big_x = x / y * luminance;
+    let big_z = (1. - x - y) / y * luminance;
+
+    Rgb::new(
+        3.2406 * big_x - 1.5372 * luminance - 0.4986 * big_z,
+        -0.9689 * big_x + 1.8758 * luminance + 0.0415 * big_z,
+        0.0557 * big_x - 0.2040 * luminance + 1.0570 * big_z,
+    )
+    .map(|channel| channel.max(0.))
+}
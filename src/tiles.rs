@@ -0,0 +1,367 @@
+use clap::ValueEnum;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use vek::Vec2;
+
+/// Rectangle of pixels, rendered as one task
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Tile {
+    /// Upper left pixel
+    pub position: Vec2<u32>,
+    pub size: Vec2<u32>,
+}
+
+impl Tile {
+    pub fn new(position: Vec2<u32>, size: Vec2<u32>) -> Self {
+        Self { position, size }
+    }
+
+    /// One past the lower right pixel, cut off at `u32::MAX`
+    pub fn end(self) -> Vec2<u32> {
+        self.position
+            .map2(self.size, |position, size| position.saturating_add(size))
+    }
+
+    pub fn contains(self, pixel: Vec2<u32>) -> bool {
+        let end = self.end();
+
+        pixel.x >= self.position.x
+            && pixel.y >= self.position.y
+            && pixel.x < end.x
+            && pixel.y < end.y
+    }
+
+    /// The part of the tile inside an image of `size`
+    pub fn clamp(self, size: Vec2<u32>) -> Self {
+        let position = Vec2::min(self.position, size);
+        let end = Vec2::min(self.end(), size);
+
+        Self::new(position, end - position)
+    }
+
+    /// Pixel positions, row by row
+    pub fn pixels(self) -> impl Iterator<Item = Vec2<u32>> {
+        let end = self.end();
+
+        (self.position.y..end.y)
+            .flat_map(move |y| (self.position.x..end.x).map(move |x| Vec2::new(x, y)))
+    }
+}
+
+impl FromStr for Tile {
+    type Err = String;
+
+    /// From `x,y,width,height`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let values = s
+            .split(',')
+            .map(|value| value.trim().parse::<u32>())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|error| error.to_string())?;
+
+        let [x, y, width, height] = values[..] else {
+            return Err("expected x,y,width,height".to_string());
+        };
+
+        if x.checked_add(width).is_none() || y.checked_add(height).is_none() {
+            return Err(format!("the tile reaches past {}", u32::MAX));
+        }
+
+        Ok(Self::new(Vec2::new(x, y), Vec2::new(width, height)))
+    }
+}
+
+impl fmt::Display for Tile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{},{},{},{}",
+            self.position.x, self.position.y, self.size.x, self.size.y
+        )
+    }
+}
+
+/// In which order tiles are handed out to the threads
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum TileOrder {
+    /// Row by row from the top
+    Rows,
+
+    /// Outwards from the middle, where the subject usually is
+    #[default]
+    Spiral,
+
+    /// Along a Hilbert curve, which keeps consecutive tiles next to each other
+    Hilbert,
+}
+
+/// Splits `region` into tiles of at most `tile_size` pixels on a side
+pub fn tiles(region: Tile, tile_size: u32, order: TileOrder) -> Vec<Tile> {
+    let tile_size = tile_size.max(1);
+    let grid = region.size.map(|size| size.div_ceil(tile_size));
+
+    let cells = match order {
+        TileOrder::Rows => Tile::new(Vec2::zero(), grid).pixels().collect(),
+        TileOrder::Spiral => spiral(grid),
+        TileOrder::Hilbert => hilbert(grid),
+    };
+
+    cells
+        .into_iter()
+        .map(|cell| {
+            let position = region.position + cell * tile_size;
+            let end = Vec2::min(position + tile_size, region.position + region.size);
+
+            Tile::new(position, end - position)
+        })
+        .collect()
+}
+
+/// Hands tiles out to the threads that claim them, in the order they are in
+#[derive(Debug)]
+pub struct TileQueue<'a> {
+    tiles: &'a [Tile],
+    next: AtomicUsize,
+}
+
+impl<'a> TileQueue<'a> {
+    pub fn new(tiles: &'a [Tile]) -> Self {
+        Self {
+            tiles,
+            next: AtomicUsize::new(0),
+        }
+    }
+
+    /// The next tile nobody has claimed yet
+    pub fn claim(&self) -> Option<Tile> {
+        let index = self.next.fetch_add(1, Ordering::Relaxed);
+
+        self.tiles.get(index).copied()
+    }
+
+    pub fn len(&self) -> usize {
+        self.tiles.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tiles.is_empty()
+    }
+}
+
+/// Cells of a `grid` sized grid, walking around the middle one
+fn spiral(grid: Vec2<u32>) -> Vec<Vec2<u32>> {
+    let count = (grid.x * grid.y) as usize;
+    let mut cells = Vec::with_capacity(count);
+
+    let mut position = grid.map(|size| (size as i64 - 1) / 2);
+    let mut push = |position: Vec2<i64>| {
+        if position.x >= 0
+            && position.y >= 0
+            && position.x < grid.x as i64
+            && position.y < grid.y as i64
+        {
+            cells.push(position.as_::<u32>());
+        }
+    };
+
+    push(position);
+
+    let directions = [
+        Vec2::new(1, 0),
+        Vec2::new(0, 1),
+        Vec2::new(-1, 0),
+        Vec2::new(0, -1),
+    ];
+    let mut step = 1;
+    let mut direction = 0;
+
+    // Legs grow by one every two turns, the last ones run partly outside the grid
+    while step <= 2 * grid.x.max(grid.y) as i64 {
+        for _ in 0..2 {
+            for _ in 0..step {
+                position += directions[direction % 4];
+                push(position);
+            }
+
+            direction += 1;
+        }
+
+        step += 1;
+    }
+
+    debug_assert_eq!(cells.len(), count);
+
+    cells
+}
+
+/// Cells of a `grid` sized grid along the Hilbert curve of the power of two square around it
+fn hilbert(grid: Vec2<u32>) -> Vec<Vec2<u32>> {
+    let side = grid.x.max(grid.y).next_power_of_two();
+
+    (0..side * side)
+        .map(|index| hilbert_point(side, index))
+        .filter(|cell| cell.x < grid.x && cell.y < grid.y)
+        .collect()
+}
+
+/// Point `index` along the Hilbert curve filling a `side` by `side` square
+fn hilbert_point(side: u32, mut index: u32) -> Vec2<u32> {
+    let mut point = Vec2::<u32>::zero();
+    let mut size = 1;
+
+    while size < side {
+        let rx = 1 & (index / 2);
+        let ry = 1 & (index ^ rx);
+
+        // Rotates the quadrant so the curve stays connected
+        if ry == 0 {
+            if rx == 1 {
+                point = Vec2::broadcast(size - 1) - point;
+            }
+
+            point = Vec2::new(point.y, point.x);
+        }
+
+        point += Vec2::new(rx, ry) * size;
+        index /= 4;
+        size *= 2;
+    }
+
+    point
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    const ORDERS: [TileOrder; 3] = [TileOrder::Rows, TileOrder::Spiral, TileOrder::Hilbert];
+
+    #[test]
+    fn tiles_cover_every_pixel_once() {
+        let regions = [
+            Tile::new(Vec2::zero(), Vec2::new(64, 64)),
+            Tile::new(Vec2::zero(), Vec2::new(100, 37)),
+            Tile::new(Vec2::new(13, 7), Vec2::new(5, 90)),
+            Tile::new(Vec2::new(3, 3), Vec2::new(1, 1)),
+        ];
+
+        for order in ORDERS {
+            for region in regions {
+                for tile_size in [1, 7, 16, 32, 200] {
+                    let mut covered = HashMap::new();
+
+                    for tile in tiles(region, tile_size, order) {
+                        assert!(tile.size.x > 0 && tile.size.y > 0);
+                        assert!(tile.size.x <= tile_size && tile.size.y <= tile_size);
+
+                        for pixel in tile.pixels() {
+                            *covered.entry(pixel).or_insert(0) += 1;
+                        }
+                    }
+
+                    let context = format!("{order:?}, region {region}, tile size {tile_size}");
+
+                    assert_eq!(covered.len(), region.pixels().count(), "{context}");
+                    assert!(
+                        region.pixels().all(|pixel| covered.get(&pixel) == Some(&1)),
+                        "{context}"
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn empty_regions_have_no_tiles() {
+        for order in ORDERS {
+            let region = Tile::new(Vec2::new(4, 4), Vec2::new(0, 10));
+            assert!(tiles(region, 8, order).is_empty());
+        }
+    }
+
+    #[test]
+    fn spiral_starts_in_the_middle() {
+        let region = Tile::new(Vec2::zero(), Vec2::new(50, 50));
+        let first = tiles(region, 10, TileOrder::Spiral)[0];
+
+        assert_eq!(first, Tile::new(Vec2::new(20, 20), Vec2::new(10, 10)));
+    }
+
+    #[test]
+    fn hilbert_tiles_are_next_to_each_other() {
+        let region = Tile::new(Vec2::zero(), Vec2::new(64, 64));
+        let tiles = tiles(region, 8, TileOrder::Hilbert);
+
+        for pair in tiles.windows(2) {
+            let step = pair[1].position.as_::<i64>() - pair[0].position.as_::<i64>();
+            assert_eq!(step.x.abs() + step.y.abs(), 8, "{} to {}", pair[0], pair[1]);
+        }
+    }
+
+    #[test]
+    fn queue_hands_tiles_out_in_order() {
+        let region = Tile::new(Vec2::zero(), Vec2::new(100, 80));
+
+        for order in ORDERS {
+            let tiles = tiles(region, 8, order);
+
+            let queue = TileQueue::new(&tiles);
+            let claimed = std::iter::from_fn(|| queue.claim()).collect::<Vec<_>>();
+            assert_eq!(claimed, tiles, "{order:?}");
+
+            // Each thread gets later tiles than the ones it had, and together they get all
+            let queue = TileQueue::new(&tiles);
+            let claimed = std::thread::scope(|scope| {
+                let threads = (0..4)
+                    .map(|_| scope.spawn(|| std::iter::from_fn(|| queue.claim()).collect()))
+                    .collect::<Vec<_>>();
+
+                threads
+                    .into_iter()
+                    .map(|thread| thread.join().unwrap())
+                    .collect::<Vec<Vec<Tile>>>()
+            });
+
+            let mut indices = Vec::new();
+            for thread in claimed {
+                let thread = thread
+                    .iter()
+                    .map(|tile| tiles.iter().position(|other| other == tile).unwrap())
+                    .collect::<Vec<_>>();
+
+                assert!(thread.windows(2).all(|pair| pair[0] < pair[1]), "{order:?}");
+                indices.extend(thread);
+            }
+
+            indices.sort();
+            assert_eq!(indices, (0..tiles.len()).collect::<Vec<_>>(), "{order:?}");
+        }
+    }
+
+    #[test]
+    fn tile_parses_what_it_displays() {
+        let tile = Tile::new(Vec2::new(1, 2), Vec2::new(30, 40));
+
+        assert_eq!(tile.to_string().parse::<Tile>(), Ok(tile));
+        assert_eq!(" 1, 2,30 ,40".parse::<Tile>(), Ok(tile));
+        assert!("1,2,3".parse::<Tile>().is_err());
+        assert!("1,2,3,x".parse::<Tile>().is_err());
+        assert!("4294967000,0,1000,1".parse::<Tile>().is_err());
+        assert!("0,4294967000,1,1000".parse::<Tile>().is_err());
+    }
+
+    #[test]
+    fn tiles_past_u32_max_are_cut_off() {
+        let tile = Tile::new(Vec2::new(u32::MAX - 2, 0), Vec2::new(1000, 1));
+
+        assert_eq!(tile.end(), Vec2::new(u32::MAX, 1));
+        assert!(tile.contains(Vec2::new(u32::MAX - 1, 0)));
+        assert_eq!(tile.pixels().count(), 2);
+        assert_eq!(
+            tile.clamp(Vec2::new(100, 100)),
+            Tile::new(Vec2::new(100, 0), Vec2::new(0, 1))
+        );
+    }
+}
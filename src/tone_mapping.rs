@@ -0,0 +1,226 @@
+use clap::{Parser, ValueEnum};
+use vek::{Mat3, Rgb, Vec3};
+
+/// Turns linear radiance into display values
+#[derive(Debug, Clone, Copy, Parser)]
+pub struct DisplaySettings {
+    /// Brightness adjustment in stops, each one doubles the brightness
+    #[arg(long, default_value_t = 0.)]
+    pub exposure: f32,
+
+    /// How radiance above 1 is brought into the displayable range
+    #[arg(long, value_enum, default_value_t)]
+    pub tone_mapper: ToneMapper,
+
+    /// Color temperature in kelvin of the light that should look white
+    #[arg(long)]
+    pub white_balance: Option<f32>,
+}
+
+impl Default for DisplaySettings {
+    fn default() -> Self {
+        Self {
+            exposure: 0.,
+            tone_mapper: ToneMapper::default(),
+            white_balance: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum ToneMapper {
+    /// Clips everything above 1
+    #[default]
+    None,
+
+    /// Compresses the luminance, keeping hues
+    Reinhard,
+
+    /// Stephen Hill's fit of the ACES reference and output transforms
+    Aces,
+
+    /// Troy Sobotka's AgX, desaturates bright colors like film does
+    Agx,
+}
+
+impl DisplaySettings {
+    /// Everything that's done before quantization, the result is sRGB encoded
+    pub fn transform(&self) -> DisplayTransform {
+        let white_balance = match self.white_balance {
+            Some(temperature) => white_balance_matrix(temperature),
+            None => Mat3::identity(),
+        };
+
+        DisplayTransform {
+            color_matrix: white_balance * 2_f32.powf(self.exposure),
+            tone_mapper: self.tone_mapper,
+        }
+    }
+}
+
+/// `DisplaySettings` with the matrices worked out once for a whole image
+#[derive(Debug, Clone, Copy)]
+pub struct DisplayTransform {
+    /// White balance and exposure
+    color_matrix: Mat3<f32>,
+    tone_mapper: ToneMapper,
+}
+
+impl DisplayTransform {
+    /// From linear sRGB radiance to sRGB encoded values from 0 to 1
+    pub fn apply(&self, color: Rgb<f32>) -> Rgb<f32> {
+        let color = multiply(self.color_matrix, color).map(|c| c.max(0.));
+
+        let color = match self.tone_mapper {
+            ToneMapper::None => color,
+            ToneMapper::Reinhard => color / (1. + luminance(color)),
+            ToneMapper::Aces => aces(color),
+            ToneMapper::Agx => agx(color),
+        };
+
+        color.map(|c| srgb_oetf(c.clamp(0., 1.)))
+    }
+}
+
+fn multiply(matrix: Mat3<f32>, color: Rgb<f32>) -> Rgb<f32> {
+    let vector = matrix * Vec3::new(color.r, color.g, color.b);
+
+    Rgb::new(vector.x, vector.y, vector.z)
+}
+
+fn luminance(color: Rgb<f32>) -> f32 {
+    0.2126 * color.r + 0.7152 * color.g + 0.0722 * color.b
+}
+
+/// sRGB transfer function, from linear to encoded
+fn srgb_oetf(value: f32) -> f32 {
+    if value <= 0.0031308 {
+        12.92 * value
+    } else {
+        1.055 * value.powf(1. / 2.4) - 0.055
+    }
+}
+
+/// Inverse of `srgb_oetf`, from encoded to linear
+pub fn srgb_eotf(value: f32) -> f32 {
+    if value <= 0.04045 {
+        value / 12.92
+    } else {
+        ((value + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+fn aces(color: Rgb<f32>) -> Rgb<f32> {
+    // sRGB to the reference transform's space, with its white point adjustment
+    let input = Mat3::new(
+        0.59719, 0.35458, 0.04823, //
+        0.07600, 0.90834, 0.01566, //
+        0.02840, 0.13383, 0.83777,
+    );
+    let output = Mat3::new(
+        1.60475, -0.53108, -0.07367, //
+        -0.10208, 1.10813, -0.00605, //
+        -0.00327, -0.07276, 1.07602,
+    );
+
+    let color = multiply(input, color).map(|v| {
+        let a = v * (v + 0.0245786) - 0.000090537;
+        let b = v * (0.983729 * v + 0.432951) + 0.238081;
+        a / b
+    });
+
+    multiply(output, color)
+}
+
+/// Benjamin Wrensch's polynomial fit of AgX with the default look
+fn agx(color: Rgb<f32>) -> Rgb<f32> {
+    const MIN_EV: f32 = -12.47393;
+    const MAX_EV: f32 = 4.026069;
+
+    let inset = Mat3::new(
+        0.842479, 0.078434, 0.079224, //
+        0.042328, 0.878469, 0.079166, //
+        0.042376, 0.078434, 0.879143,
+    );
+    let outset = Mat3::new(
+        1.196879, -0.098021, -0.099030, //
+        -0.052897, 1.151903, -0.098961, //
+        -0.052972, -0.098043, 1.151074,
+    );
+
+    // Logarithmic encoding, then the sigmoid
+    let color = multiply(inset, color).map(|c| {
+        let x = (c.max(1e-10).log2().clamp(MIN_EV, MAX_EV) - MIN_EV) / (MAX_EV - MIN_EV);
+
+        let x2 = x * x;
+        let x4 = x2 * x2;
+
+        15.5 * x4 * x2 - 40.14 * x4 * x + 31.96 * x4 - 6.868 * x2 * x + 0.4298 * x2 + 0.1191 * x
+            - 0.00232
+    });
+
+    // The fit gives display encoded values, back to linear for the sRGB encoding
+    multiply(outset, color).map(|c| c.max(0.).powf(2.2))
+}
+
+/// Von Kries adaptation in the Bradford cone space, from a black body of `temperature` to
+/// the D65 white of sRGB
+fn white_balance_matrix(temperature: f32) -> Mat3<f32> {
+    let srgb_to_xyz = Mat3::new(
+        0.4124, 0.3576, 0.1805, //
+        0.2126, 0.7152, 0.0722, //
+        0.0193, 0.1192, 0.9505,
+    );
+    let xyz_to_srgb = Mat3::new(
+        3.2406, -1.5372, -0.4986, //
+        -0.9689, 1.8758, 0.0415, //
+        0.0557, -0.2040, 1.0570,
+    );
+    let bradford = Mat3::new(
+        0.8951, 0.2664, -0.1614, //
+        -0.7502, 1.7135, 0.0367, //
+        0.0389, -0.0685, 1.0296,
+    );
+    let inverse_bradford = Mat3::new(
+        0.9870, -0.1471, 0.1600, //
+        0.4323, 0.5184, 0.0493, //
+        -0.0085, 0.0400, 0.9685,
+    );
+
+    let source = bradford * planckian_white(temperature);
+    let target = bradford * Vec3::new(0.95047, 1., 1.08883);
+
+    let scale = target / source;
+    let adaptation = Mat3::new(
+        scale.x, 0., 0., //
+        0., scale.y, 0., //
+        0., 0., scale.z,
+    );
+
+    xyz_to_srgb * inverse_bradford * adaptation * bradford * srgb_to_xyz
+}
+
+/// XYZ of a black body with a luminance of 1, from Kim et al.'s fit of the Planckian locus
+/// between 1667 K and 25000 K
+fn planckian_white(temperature: f32) -> Vec3<f32> {
+    let t = temperature.clamp(1667., 25000.);
+    let (t2, t3) = (t * t, t * t * t);
+
+    let x = if t <= 4000. {
+        -0.2661239e9 / t3 - 0.2343589e6 / t2 + 0.8776956e3 / t + 0.179910
+    } else {
+        -3.0258469e9 / t3 + 2.107038e6 / t2 + 0.2226347e3 / t + 0.240390
+    };
+
+    let (x2, x3) = (x * x, x * x * x);
+
+    let y = if t <= 2222. {
+        -1.1063814 * x3 - 1.34811 * x2 + 2.185558 * x - 0.202197
+    } else if t <= 4000. {
+        -0.9549476 * x3 - 1.374186 * x2 + 2.09137 * x - 0.167489
+    } else {
+        3.081758 * x3 - 5.873387 * x2 + 3.75113 * x - 0.370015
+    };
+
+    Vec3::new(x / y, 1., (1. - x - y) / y)
+}